@@ -0,0 +1,619 @@
+pub mod worker;
+
+use crate::visualizers::BeatInfo;
+use spectrum_analyzer::{
+    scaling::divide_by_N, samples_fft_to_spectrum, windows::hann_window, FrequencyLimit,
+    FrequencySpectrum,
+};
+use std::{collections::VecDeque, ops::RangeInclusive, time::Duration};
+
+/// Number of samples fed to each FFT.
+pub const FFT_SIZE: usize = 2048;
+/// Live analysis steps per second of audio, whatever the sample rate. The beat
+/// detector's history of this many steps then spans one second.
+pub const STEPS_PER_SECOND: u32 = 43;
+
+/// Samples between live analysis steps at `rate` Hz.
+pub fn hop_size(rate: u32) -> usize {
+    (rate / STEPS_PER_SECOND).max(1) as usize
+}
+
+// --- Beat Detector ---
+
+pub struct BeatDetector {
+    energy_history: Vec<f32>,
+    history_size: usize,
+    sensitivity: f32,
+    /// Audio position of the last beat counted.
+    last_beat: Duration,
+    intervals: VecDeque<Duration>,
+    pub total_beats: usize,
+}
+
+impl BeatDetector {
+    pub fn new(history_size: usize, sensitivity: f32) -> Self {
+        Self {
+            energy_history: Vec::with_capacity(history_size),
+            history_size,
+            sensitivity,
+            last_beat: Duration::ZERO,
+            intervals: VecDeque::with_capacity(10),
+            total_beats: 0,
+        }
+    }
+
+    /// Looks for a beat in the frame at audio position `time`. Intervals are measured
+    /// in audio time, so the tempo doesn't depend on when the frame is analyzed.
+    pub fn detect(&mut self, spectrum_data: &FrequencySpectrum, time: Duration) -> bool {
+        let mut low_energy = 0.0;
+        let mut count = 0;
+        
+        // Use a weighted average where lower frequencies (20-60Hz) are prioritized
+        // as they represent the "thump" of the kick drum more accurately.
+        for (freq, val) in spectrum_data.to_map().iter() {
+            let f = *freq as f32;
+            let v = *val;
+            if f >= 20.0 && f <= 150.0 {
+                let weight = if f <= 60.0 { 1.5 } else { 1.0 };
+                low_energy += v * weight;
+                count += 1;
+            }
+        }
+
+        if count == 0 {
+            return false;
+        }
+
+        let avg_low_energy = low_energy / count as f32;
+
+        if self.energy_history.is_empty() {
+            self.energy_history.push(avg_low_energy);
+            return false;
+        }
+
+        // Calculate both average and variance for a more dynamic threshold
+        let history_avg: f32 =
+            self.energy_history.iter().sum::<f32>() / self.energy_history.len() as f32;
+        
+        let variance: f32 = self.energy_history.iter()
+            .map(|e| (e - history_avg).powi(2))
+            .sum::<f32>() / self.energy_history.len() as f32;
+        
+        // A "beat" is a peak that stands out significantly from the local noise floor.
+        // We use a combination of sensitivity * average and a variance-based offset.
+        let dynamic_threshold = self.sensitivity * history_avg + variance.sqrt() * 0.5;
+
+        self.energy_history.push(avg_low_energy);
+        if self.energy_history.len() > self.history_size {
+            self.energy_history.remove(0);
+        }
+
+        let is_beat = avg_low_energy > dynamic_threshold && avg_low_energy > 0.01;
+
+        if is_beat {
+            let duration = time.saturating_sub(self.last_beat);
+            // Limit to ~200 BPM (300ms) to avoid double triggers
+            if duration.as_millis() > 300 {
+                self.intervals.push_back(duration);
+                if self.intervals.len() > 15 {
+                    self.intervals.pop_front();
+                }
+                self.last_beat = time;
+                self.total_beats += 1;
+            }
+        }
+
+        is_beat
+    }
+
+    /// Threshold multiplier over the average bass energy; lower values detect more beats.
+    pub fn sensitivity(&self) -> f32 {
+        self.sensitivity
+    }
+
+    pub fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity;
+    }
+
+    pub fn get_bpm(&self) -> f32 {
+        if self.intervals.len() < 3 {
+            return 0.0;
+        }
+        
+        // Use a median-like approach: sort intervals and pick the middle range 
+        // to ignore outliers (missed beats or accidental double triggers).
+        let mut sorted_intervals: Vec<u128> = self.intervals.iter()
+            .map(|d| d.as_millis())
+            .collect();
+        sorted_intervals.sort_unstable();
+        
+        let mid = sorted_intervals.len() / 2;
+        let median_ms = if sorted_intervals.len() % 2 == 0 {
+            (sorted_intervals[mid - 1] + sorted_intervals[mid]) as f32 / 2.0
+        } else {
+            sorted_intervals[mid] as f32
+        };
+
+        if median_ms == 0.0 {
+            0.0
+        } else {
+            60000.0 / median_ms
+        }
+    }
+}
+
+// --- Utils ---
+
+pub fn get_peak_frequency(spectrum: &FrequencySpectrum) -> (u32, f32) {
+    let mut max_val = 0.0;
+    let mut peak_freq = 0;
+    for (freq, val) in spectrum.to_map().iter() {
+        if *val > max_val {
+            max_val = *val;
+            peak_freq = *freq;
+        }
+    }
+    (peak_freq, max_val)
+}
+
+/// Mean magnitude of the spectrum between `min_f` and `max_f` Hz.
+pub fn get_band_energy(spectrum: &FrequencySpectrum, min_f: f32, max_f: f32) -> f32 {
+    let mut energy = 0.0;
+    let mut count = 0;
+    for (freq, val) in spectrum.to_map().iter() {
+        let f = *freq as f32;
+        if f >= min_f && f <= max_f {
+            energy += val;
+            count += 1;
+        }
+    }
+    if count > 0 { energy / count as f32 } else { 0.0 }
+}
+
+/// Mean energy of four broad bands, for driving things outside the terminal.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bands {
+    /// 20-60 Hz
+    pub sub: f32,
+    /// 60-250 Hz
+    pub bass: f32,
+    /// 250-2000 Hz
+    pub mid: f32,
+    /// 2-15 kHz
+    pub high: f32,
+}
+
+impl Bands {
+    pub fn from_spectrum(spectrum: &FrequencySpectrum) -> Self {
+        Self {
+            sub: get_band_energy(spectrum, 20.0, 60.0),
+            bass: get_band_energy(spectrum, 60.0, 250.0),
+            mid: get_band_energy(spectrum, 250.0, 2000.0),
+            high: get_band_energy(spectrum, 2000.0, 15000.0),
+        }
+    }
+}
+
+/// The spectrum reduced to `count` bins spaced logarithmically from 20 Hz to 20 kHz,
+/// each the peak magnitude within its range.
+pub fn log_bins(spectrum: &FrequencySpectrum, count: usize) -> Vec<f32> {
+    let (min_log, max_log) = (20f32.ln(), 20_000f32.ln());
+    let edge = |i: usize| (min_log + (max_log - min_log) * i as f32 / count as f32).exp();
+    let mut bins = vec![0.0f32; count];
+    for (freq, val) in spectrum.data() {
+        let f = freq.val();
+        if f < 20.0 || count == 0 {
+            continue;
+        }
+        let i = (((f.ln() - min_log) / (max_log - min_log)) * count as f32) as usize;
+        if let Some(bin) = bins.get_mut(i) {
+            *bin = bin.max(val.val());
+        }
+    }
+    // Low bins can be narrower than the FFT resolution; borrow the nearest value.
+    for (i, bin) in bins.iter_mut().enumerate() {
+        if *bin == 0.0 {
+            let center = (edge(i) * edge(i + 1)).sqrt();
+            *bin = spectrum.freq_val_closest(center).1.val();
+        }
+    }
+    bins
+}
+
+/// Spectrum of the most recent [`FFT_SIZE`] samples, or `None` until that many have arrived.
+pub fn compute_spectrum(samples: &[f32], sample_rate: u32) -> Option<FrequencySpectrum> {
+    if samples.len() < FFT_SIZE {
+        return None;
+    }
+    let window = &samples[samples.len() - FFT_SIZE..];
+    let hann_window = hann_window(window);
+
+    samples_fft_to_spectrum(
+        &hann_window,
+        sample_rate,
+        FrequencyLimit::Range(20., 20_000.),
+        Some(&divide_by_N),
+    )
+    .ok()
+}
+
+/// Root-mean-square level of the most recent [`FFT_SIZE`] samples.
+pub fn rms(samples: &[f32]) -> f32 {
+    let window = &samples[samples.len().saturating_sub(FFT_SIZE)..];
+    if window.is_empty() {
+        return 0.0;
+    }
+    (window.iter().map(|s| s * s).sum::<f32>() / window.len() as f32).sqrt()
+}
+
+// --- Manual Tempo ---
+
+/// Taps further apart than this start a new measurement.
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+/// Number of recent taps averaged into the tapped tempo.
+const MAX_TAPS: usize = 8;
+/// Tempos that tapping, halving and doubling can reach.
+const TEMPO_RANGE: RangeInclusive<f32> = 30.0..=300.0;
+
+/// Beats generated at a fixed tempo, independent of the detector.
+struct Lock {
+    period: Duration,
+    next_beat: Duration,
+}
+
+impl Lock {
+    fn new(bpm: f32, next_beat: Duration) -> Self {
+        Self {
+            period: Duration::from_secs_f32(60.0 / bpm.clamp(20.0, 400.0)),
+            next_beat,
+        }
+    }
+
+    /// Whether a beat falls due at `now`; skips any that were missed.
+    fn tick(&mut self, now: Duration) -> bool {
+        if now < self.next_beat {
+            return false;
+        }
+        while self.next_beat <= now {
+            self.next_beat += self.period;
+        }
+        true
+    }
+
+    /// Moves the phase halfway towards an onset close to where a beat was expected.
+    fn resync(&mut self, onset: Duration) {
+        let window = self.period / 6;
+        let previous = self.next_beat.saturating_sub(self.period);
+        if onset >= previous && onset - previous <= window {
+            // Late: the generated beat came before the music's.
+            self.next_beat += (onset - previous) / 2;
+        } else if onset < self.next_beat && self.next_beat - onset <= window {
+            // Early: pull the next beat in.
+            self.next_beat -= (self.next_beat - onset) / 2;
+        }
+    }
+}
+
+/// Manual corrections on top of the detected tempo: tap tempo, halve/double, and a
+/// lock that generates beats from the manual tempo instead of the detector.
+///
+/// Everything is timed in analysis time, the audio position of the latest
+/// [`Analyzer::update`], so taps, the lock and detected beats share one clock.
+pub struct Tempo {
+    /// Analysis time of the latest update.
+    now: Duration,
+    taps: VecDeque<Duration>,
+    /// Tempo from tapping. Overrides the detector's estimate until the tempo is
+    /// locked and released, or the detector finds a beat after tapping has stopped.
+    tapped: Option<f32>,
+    /// Factor from halve/double applied to the detected tempo.
+    scale: f32,
+    lock: Option<Lock>,
+    /// Whether detected beats nudge the phase of a locked tempo.
+    pub resync: bool,
+}
+
+impl Tempo {
+    fn new() -> Self {
+        Self {
+            now: Duration::ZERO,
+            taps: VecDeque::with_capacity(MAX_TAPS),
+            tapped: None,
+            scale: 1.0,
+            lock: None,
+            resync: true,
+        }
+    }
+
+    /// The tempo in effect, given the detector's estimate.
+    fn bpm(&self, detected: f32) -> f32 {
+        match (&self.lock, self.tapped) {
+            (Some(lock), _) => 60.0 / lock.period.as_secs_f32(),
+            (None, Some(tapped)) => tapped,
+            (None, None) => detected * self.scale,
+        }
+    }
+
+    /// Registers a tap. From the second tap on, sets the tempo from the average
+    /// interval, within 30-300 BPM; when locked, each tap also marks a beat.
+    pub fn tap(&mut self) {
+        let now = self.now;
+        if self.taps.back().is_some_and(|&last| now - last > TAP_TIMEOUT) {
+            self.taps.clear();
+        }
+        self.taps.push_back(now);
+        if self.taps.len() > MAX_TAPS {
+            self.taps.pop_front();
+        }
+
+        let tapped = (self.taps.len() >= 2).then(|| {
+            let span = now - self.taps[0];
+            let bpm = 60.0 * (self.taps.len() - 1) as f32 / span.as_secs_f32();
+            bpm.clamp(*TEMPO_RANGE.start(), *TEMPO_RANGE.end())
+        });
+        if tapped.is_some() {
+            self.tapped = tapped;
+        }
+        if let Some(lock) = &mut self.lock {
+            // A tap while locked marks a beat, so tapping along also fixes the phase.
+            let bpm = tapped.unwrap_or(60.0 / lock.period.as_secs_f32());
+            *lock = Lock::new(bpm, now);
+        }
+    }
+
+    /// Multiplies the tempo in effect by `factor` (e.g. 0.5 or 2), within 30-300 BPM.
+    pub fn scale(&mut self, factor: f32, detected: f32) {
+        let bpm = self.bpm(detected);
+        if bpm <= 0.0 || !TEMPO_RANGE.contains(&(bpm * factor)) {
+            return;
+        }
+        if let Some(lock) = &mut self.lock {
+            *lock = Lock::new(bpm * factor, lock.next_beat);
+        } else if let Some(tapped) = &mut self.tapped {
+            *tapped *= factor;
+        } else {
+            self.scale *= factor;
+        }
+    }
+
+    /// Locks beats to the tempo in effect, or goes back to detection (dropping any
+    /// tapped tempo). Returns whether the tempo is now locked.
+    pub fn toggle_lock(&mut self, detected: f32) -> bool {
+        if self.lock.take().is_some() {
+            self.tapped = None;
+            return false;
+        }
+        let bpm = self.bpm(detected);
+        if bpm <= 0.0 {
+            return false;
+        }
+        // Keep the phase of the last tap if there was a recent one.
+        let now = self.now;
+        let next_beat = match self.taps.back() {
+            Some(&tap) if now - tap <= TAP_TIMEOUT => tap,
+            _ => now,
+        };
+        let mut lock = Lock::new(bpm, next_beat);
+        lock.tick(now);
+        self.lock = Some(lock);
+        true
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    /// Whether tapping has stopped for longer than a tap could follow on.
+    fn taps_stale(&self) -> bool {
+        self.taps.back().is_none_or(|&last| self.now - last > TAP_TIMEOUT)
+    }
+}
+
+/// Beat detection plus the short hold that keeps `is_beat` set for a few frames,
+/// so visualizers have time to react to it.
+///
+/// While [`Tempo`] is locked, beats come from the manual tempo instead.
+pub struct Analyzer {
+    pub detector: BeatDetector,
+    pub tempo: Tempo,
+    is_beat: bool,
+    beat_timer: u32,
+    total_beats: usize,
+    /// The detector's beat count as of the last update.
+    detected_beats: usize,
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        Self {
+            detector: BeatDetector::new(STEPS_PER_SECOND as usize, 1.5),
+            tempo: Tempo::new(),
+            is_beat: false,
+            beat_timer: 0,
+            total_beats: 0,
+            detected_beats: 0,
+        }
+    }
+
+    /// Beats reported so far, detected or generated.
+    pub fn total_beats(&self) -> usize {
+        self.total_beats
+    }
+
+    /// Tempo estimate from the detector alone.
+    pub fn detected_bpm(&self) -> f32 {
+        self.detector.get_bpm()
+    }
+
+    /// Feeds the analysis frame at audio position `time` to the beat detector and
+    /// returns the resulting beat state.
+    pub fn update(&mut self, spectrum: Option<&FrequencySpectrum>, time: Duration) -> BeatInfo {
+        let onset = spectrum.is_some_and(|spectrum| self.detector.detect(spectrum, time));
+        let new_beats = self.detector.total_beats - self.detected_beats;
+        self.detected_beats = self.detector.total_beats;
+        self.tempo.now = time;
+
+        let beat = match &mut self.tempo.lock {
+            Some(lock) => {
+                if new_beats > 0 && self.tempo.resync {
+                    lock.resync(time);
+                }
+                let due = lock.tick(time);
+                self.total_beats += due as usize;
+                due
+            }
+            None => {
+                // Detection has moved on since the tapping; trust it again.
+                if new_beats > 0 && self.tempo.taps_stale() {
+                    self.tempo.tapped = None;
+                }
+                self.total_beats += new_beats;
+                onset
+            }
+        };
+        if beat {
+            self.is_beat = true;
+            self.beat_timer = 5;
+        }
+
+        if self.beat_timer > 0 {
+            self.beat_timer -= 1;
+        } else {
+            self.is_beat = false;
+        }
+
+        BeatInfo {
+            is_beat: self.is_beat,
+            bpm: self.tempo.bpm(self.detector.get_bpm()),
+            total_beats: self.total_beats,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tapped_tempo_stays_in_range() {
+        let mut tempo = Tempo::new();
+        // Two taps in quick succession would otherwise be thousands of BPM.
+        tempo.tap();
+        tempo.tap();
+        assert_eq!(tempo.bpm(0.0), 300.0);
+    }
+
+    fn secs(seconds: f32) -> Duration {
+        Duration::from_secs_f32(seconds)
+    }
+
+    /// Taps at each of `times`, in analysis time.
+    fn tap_at(tempo: &mut Tempo, times: &[f32]) {
+        for &time in times {
+            tempo.now = secs(time);
+            tempo.tap();
+        }
+    }
+
+    #[test]
+    fn taps_set_the_tempo_from_their_average_interval() {
+        let mut tempo = Tempo::new();
+        tap_at(&mut tempo, &[1.0, 1.5, 2.0, 2.5]);
+        assert_eq!(tempo.bpm(90.0), 120.0);
+        // After a pause the next taps start a new measurement.
+        tap_at(&mut tempo, &[10.0, 11.0]);
+        assert_eq!(tempo.bpm(90.0), 60.0);
+    }
+
+    #[test]
+    fn halve_and_double_scale_the_tempo_in_effect() {
+        let mut tempo = Tempo::new();
+        tempo.scale(2.0, 80.0);
+        assert_eq!(tempo.bpm(80.0), 160.0);
+        tempo.scale(0.5, 80.0);
+        tempo.scale(0.5, 80.0);
+        assert_eq!(tempo.bpm(80.0), 40.0);
+        // Nothing to scale without a tempo, and nothing beyond 30-300 BPM.
+        tempo.scale(0.5, 80.0);
+        assert_eq!(tempo.bpm(80.0), 40.0);
+        assert_eq!(tempo.bpm(0.0), 0.0);
+
+        let mut tempo = Tempo::new();
+        tap_at(&mut tempo, &[1.0, 1.5]);
+        tempo.scale(0.5, 80.0);
+        assert_eq!(tempo.bpm(80.0), 60.0);
+
+        let mut tempo = Tempo::new();
+        assert!(tempo.toggle_lock(100.0));
+        tempo.scale(2.0, 80.0);
+        assert!((tempo.bpm(80.0) - 200.0).abs() < 0.01);
+    }
+
+    /// Steps `analyzer` with no audio every `step` until `until`, returning the
+    /// analysis times at which beats were reported.
+    fn beats_until(analyzer: &mut Analyzer, from: f32, until: f32, step: f32) -> Vec<f32> {
+        let mut beats = Vec::new();
+        let mut time = from;
+        while time < until {
+            let before = analyzer.total_beats();
+            analyzer.update(None, secs(time));
+            if analyzer.total_beats() > before {
+                beats.push(time);
+            }
+            time += step;
+        }
+        beats
+    }
+
+    #[test]
+    fn lock_generates_beats_at_the_tempo() {
+        let mut analyzer = Analyzer::new();
+        analyzer.update(None, secs(1.0));
+        tap_at(&mut analyzer.tempo, &[1.0, 1.5, 2.0]);
+        assert!(analyzer.tempo.toggle_lock(0.0));
+        assert_eq!(analyzer.total_beats(), 0);
+
+        // 120 BPM from the last tap: a beat every half second of analysis time,
+        // whatever the wall clock does.
+        let beats = beats_until(&mut analyzer, 2.01, 4.3, 0.01);
+        assert_eq!(beats.len(), 4);
+        for (beat, expected) in beats.iter().zip([2.5, 3.0, 3.5, 4.0]) {
+            assert!((beat - expected).abs() < 0.015, "{:?}", beats);
+        }
+
+        // Released, beats come from the detector again: none without audio.
+        assert!(!analyzer.tempo.toggle_lock(0.0));
+        assert!(beats_until(&mut analyzer, 4.3, 6.0, 0.01).is_empty());
+        assert_eq!(analyzer.tempo.bpm(90.0), 90.0);
+    }
+
+    #[test]
+    fn resync_pulls_the_phase_towards_onsets() {
+        // A beat every second, the next one due at 10 s.
+        let mut lock = Lock::new(60.0, secs(10.0));
+        // An onset 0.1 s late after the beat at 9 s moves the phase on by half that.
+        lock.resync(secs(9.1));
+        assert!(lock.next_beat.abs_diff(secs(10.05)) < Duration::from_millis(1));
+        // One 0.1 s early pulls the next beat in by half.
+        lock.resync(secs(9.95));
+        assert!(lock.next_beat.abs_diff(secs(10.0)) < Duration::from_millis(1));
+        // Onsets far from any beat are ignored.
+        lock.resync(secs(9.5));
+        assert!(lock.next_beat.abs_diff(secs(10.0)) < Duration::from_millis(1));
+    }
+
+    #[test]
+    fn tapped_tempo_gives_way_to_later_detected_beats() {
+        let mut analyzer = Analyzer::new();
+        tap_at(&mut analyzer.tempo, &[1.0, 1.5]);
+        let detected_beat = |analyzer: &mut Analyzer, time: f32| {
+            analyzer.detector.total_beats += 1;
+            analyzer.update(None, secs(time)).bpm
+        };
+        // Still tapping along: the tapped tempo stands.
+        assert_eq!(detected_beat(&mut analyzer, 2.0), 120.0);
+        assert_eq!(analyzer.update(None, secs(5.0)).bpm, 120.0);
+        // A beat after the taps have stopped hands back to detection.
+        assert_eq!(detected_beat(&mut analyzer, 5.5), 0.0);
+    }
+}
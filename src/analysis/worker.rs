@@ -0,0 +1,197 @@
+//! Analysis on its own thread: one FFT and beat detection step per hop of audio,
+//! so the detector and the outputs see the same frames however fast the display is
+//! drawn.
+
+use super::{compute_spectrum, hop_size, rms, Analyzer, FFT_SIZE};
+use crate::{audio::Samples, metrics::METRICS, output::Outputs, visualizers::BeatInfo};
+use anyhow::Result;
+use spectrum_analyzer::FrequencySpectrum;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long the thread sleeps when no new hop has arrived.
+const POLL: Duration = Duration::from_millis(2);
+/// With no audio for this long the input is taken to have stopped; the spectrum is
+/// dropped and the analyzer keeps stepping on its own, so a locked tempo still beats.
+const STALLED: Duration = Duration::from_millis(500);
+/// Step interval while stalled.
+const IDLE_STEP: Duration = Duration::from_millis(20);
+
+/// The result of the most recent analysis step.
+#[derive(Clone)]
+pub struct Snapshot {
+    /// `None` until a full FFT window has arrived, or while the input is stalled.
+    pub spectrum: Option<Arc<FrequencySpectrum>>,
+    pub beat_info: BeatInfo,
+}
+
+/// What the thread hands over to whoever draws.
+struct Published {
+    latest: Snapshot,
+    /// Beats reported since the last [`AnalysisWorker::take`].
+    pending_beats: usize,
+}
+
+/// A running analysis thread; it stops when this is dropped.
+pub struct AnalysisWorker {
+    analyzer: Arc<Mutex<Analyzer>>,
+    published: Arc<Mutex<Published>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl AnalysisWorker {
+    /// Starts analyzing `samples` with `analyzer`, from the next hop on, and feeds
+    /// every step to `outputs`.
+    pub fn spawn(
+        samples: Arc<Mutex<Samples>>,
+        analyzer: Analyzer,
+        mut outputs: Outputs,
+    ) -> Result<Self> {
+        let analyzer = Arc::new(Mutex::new(analyzer));
+        let published = Arc::new(Mutex::new(Published {
+            latest: Snapshot {
+                spectrum: None,
+                beat_info: BeatInfo {
+                    is_beat: false,
+                    bpm: 0.0,
+                    total_beats: 0,
+                },
+            },
+            pending_beats: 0,
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (shared, output, stopped) = (analyzer.clone(), published.clone(), stop.clone());
+        thread::Builder::new()
+            .name("analysis".into())
+            .spawn(move || {
+                let (mut last_end, mut next_end) = {
+                    let samples = samples.lock().unwrap();
+                    (samples.written, samples.written + hop_size(samples.rate) as u64)
+                };
+                // Audio analyzed so far. Summed window by window rather than taken
+                // from `written`, which spans streams opened at different rates.
+                let mut audio_time = Duration::ZERO;
+                let mut last_audio = Instant::now();
+                let mut last_step = Instant::now();
+                while !stopped.load(Ordering::SeqCst) {
+                    if let Some(window) = next_window(&samples, &mut next_end) {
+                        let advanced = window.end - last_end;
+                        audio_time += Duration::from_secs_f64(advanced as f64 / window.rate as f64);
+                        last_end = window.end;
+                        let started = Instant::now();
+                        let spectrum = compute_spectrum(&window.samples, window.rate);
+                        METRICS.fft_time.observe(started.elapsed());
+                        METRICS.input_level.set(rms(&window.samples));
+                        let spectrum = spectrum.map(Arc::new);
+                        step(&shared, &output, &mut outputs, spectrum, audio_time);
+                        last_audio = Instant::now();
+                        last_step = last_audio;
+                    } else if last_audio.elapsed() >= STALLED && last_step.elapsed() >= IDLE_STEP {
+                        // Analysis time runs on without audio, for a locked tempo.
+                        audio_time += last_step.elapsed();
+                        step(&shared, &output, &mut outputs, None, audio_time);
+                        last_step = Instant::now();
+                    } else {
+                        thread::sleep(POLL);
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            analyzer,
+            published,
+            stop,
+        })
+    }
+
+    /// The analyzer, for tempo and sensitivity changes. The thread waits while the
+    /// guard is held, so keep it short.
+    pub fn analyzer(&self) -> MutexGuard<'_, Analyzer> {
+        self.analyzer.lock().unwrap()
+    }
+
+    /// The latest snapshot. `is_beat` is also set if any beat was reported since the
+    /// previous call, so a slow frame never misses one.
+    pub fn take(&self) -> Snapshot {
+        let mut published = self.published.lock().unwrap();
+        let mut snapshot = published.latest.clone();
+        if published.pending_beats > 0 {
+            snapshot.beat_info.is_beat = true;
+            published.pending_beats = 0;
+        }
+        snapshot
+    }
+}
+
+impl Drop for AnalysisWorker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Runs one analyzer update for audio position `time`, publishes the result and
+/// passes it on to the outputs.
+fn step(
+    analyzer: &Mutex<Analyzer>,
+    published: &Mutex<Published>,
+    outputs: &mut Outputs,
+    spectrum: Option<Arc<FrequencySpectrum>>,
+    time: Duration,
+) {
+    let beat_info = analyzer.lock().unwrap().update(spectrum.as_deref(), time);
+    METRICS.bpm.set(beat_info.bpm);
+    METRICS.total_beats.store(beat_info.total_beats as u64, Ordering::Relaxed);
+
+    if let Some(spectrum) = &spectrum {
+        outputs.update(spectrum, &beat_info);
+    }
+
+    let mut published = published.lock().unwrap();
+    published.pending_beats += beat_info
+        .total_beats
+        .saturating_sub(published.latest.beat_info.total_beats);
+    published.latest = Snapshot {
+        spectrum,
+        beat_info,
+    };
+}
+
+/// An FFT window copied out of the shared samples.
+struct Window {
+    samples: Vec<f32>,
+    rate: u32,
+    /// Position of the window's last sample in [`Samples::written`] terms.
+    end: u64,
+}
+
+/// Copies out the FFT window ending at the next hop once the audio for it has
+/// arrived. Hops are a fixed fraction of a second at the current sample rate, so
+/// nothing is analyzed until the rate is known.
+fn next_window(samples: &Mutex<Samples>, next_end: &mut u64) -> Option<Window> {
+    let samples = samples.lock().unwrap();
+    if samples.rate == 0 || samples.written < *next_end {
+        return None;
+    }
+    let mut behind = (samples.written - *next_end) as usize;
+    if behind + FFT_SIZE > samples.recent.len() {
+        // The window has already scrolled out (or the buffer was cleared on a
+        // reconnect): carry on from the newest audio.
+        *next_end = samples.written;
+        behind = 0;
+    }
+    let end = samples.recent.len() - behind;
+    let window = Window {
+        samples: samples.recent[end.saturating_sub(FFT_SIZE)..end].to_vec(),
+        rate: samples.rate,
+        end: *next_end,
+    };
+    *next_end += hop_size(samples.rate) as u64;
+    Some(window)
+}
@@ -0,0 +1,264 @@
+//! Built-in test signals, for checking visualizers and beat detection without
+//! audio hardware. Output is paced in real time and repeatable from run to run.
+
+use super::{push_frames, Samples};
+use anyhow::{anyhow, Result};
+use rand::{rngs::SmallRng, RngExt, SeedableRng};
+use std::{
+    f32::consts::TAU,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const SAMPLE_RATE: u32 = 48_000;
+
+/// Signals available as `--input test:<kind>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TestSignal {
+    /// Logarithmic sine sweep from 20 Hz to 20 kHz, repeating every `seconds`.
+    Sweep { seconds: f32 },
+    WhiteNoise,
+    PinkNoise,
+    /// Kick drum plus click on every beat.
+    Click { bpm: f32 },
+    /// Equal-level sines at these frequencies.
+    Chord(Vec<f32>),
+    /// The same tone in both channels with the right one's phase turning a full
+    /// circle every few seconds; the mono mix swells and cancels out as it does.
+    Phase { hz: f32 },
+}
+
+impl TestSignal {
+    /// Parses `<kind>[:<arg>]`, e.g. `click:128` or `chord:220,277,330`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (kind, arg) = match spec.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (spec, None),
+        };
+        let number = |default: f32| -> Result<f32> {
+            let value = match arg {
+                Some(arg) => arg
+                    .parse()
+                    .map_err(|_| anyhow!("test:{} expects a number, got '{}'", kind, arg))?,
+                None => default,
+            };
+            if value.is_finite() && value > 0.0 {
+                Ok(value)
+            } else {
+                Err(anyhow!("test:{} needs a positive value", kind))
+            }
+        };
+
+        Ok(match kind {
+            "sweep" => TestSignal::Sweep { seconds: number(10.0)? },
+            "white" => TestSignal::WhiteNoise,
+            "pink" => TestSignal::PinkNoise,
+            "click" => TestSignal::Click { bpm: number(120.0)? },
+            "chord" => match arg {
+                Some(list) => TestSignal::Chord(
+                    list.split(',')
+                        .map(|hz| match hz.trim().parse::<f32>() {
+                            Ok(hz) if hz > 0.0 => Ok(hz),
+                            _ => Err(anyhow!("invalid chord frequency '{}'", hz)),
+                        })
+                        .collect::<Result<_>>()?,
+                ),
+                // A major triad.
+                None => TestSignal::Chord(vec![220.0, 277.18, 329.63]),
+            },
+            "phase" => TestSignal::Phase { hz: number(440.0)? },
+            _ => {
+                return Err(anyhow!(
+                    "unknown test signal '{}'; expected sweep, white, pink, click, chord or phase",
+                    kind
+                ))
+            }
+        })
+    }
+}
+
+/// Produces stereo frames of a [`TestSignal`] one at a time.
+struct Synth {
+    signal: TestSignal,
+    frame: u64,
+    /// Oscillator phase in cycles, per tone.
+    phases: Vec<f32>,
+    rng: SmallRng,
+    /// Filter state for pink noise.
+    pink: [f32; 3],
+}
+
+impl Synth {
+    fn new(signal: TestSignal) -> Self {
+        let tones = match &signal {
+            TestSignal::Chord(tones) => tones.len(),
+            _ => 1,
+        };
+        Self {
+            signal,
+            frame: 0,
+            phases: vec![0.0; tones],
+            rng: SmallRng::seed_from_u64(0x5EED),
+            pink: [0.0; 3],
+        }
+    }
+
+    fn next(&mut self) -> (f32, f32) {
+        // Position within a repeating period, in seconds; kept exact on long runs.
+        let t = self.frame as f64 / SAMPLE_RATE as f64;
+        let within = |period: f32| (t % period as f64) as f32;
+        self.frame += 1;
+
+        match &self.signal {
+            TestSignal::Sweep { seconds } => {
+                let progress = within(*seconds) / seconds;
+                let hz = 20.0 * 1000f32.powf(progress);
+                let s = 0.5 * sine(&mut self.phases[0], hz);
+                (s, s)
+            }
+            TestSignal::WhiteNoise => {
+                let s = 0.5 * self.rng.random_range(-1.0..1.0);
+                (s, s)
+            }
+            TestSignal::PinkNoise => {
+                // Paul Kellet's economy filter: -3 dB/octave within about 0.5 dB.
+                let white: f32 = self.rng.random_range(-1.0..1.0);
+                let [b0, b1, b2] = &mut self.pink;
+                *b0 = 0.99765 * *b0 + white * 0.0990460;
+                *b1 = 0.96300 * *b1 + white * 0.2965164;
+                *b2 = 0.57000 * *b2 + white * 1.0526913;
+                let s = 0.15 * (*b0 + *b1 + *b2 + white * 0.1848);
+                (s, s)
+            }
+            TestSignal::Click { bpm } => {
+                let since_beat = within(60.0 / bpm);
+                let kick = (TAU * 55.0 * since_beat).sin() * (-since_beat / 0.08).exp();
+                let click = (TAU * 1000.0 * since_beat).sin() * (-since_beat / 0.002).exp();
+                let s = 0.7 * kick + 0.3 * click;
+                (s, s)
+            }
+            TestSignal::Chord(tones) => {
+                let level = 0.6 / tones.len() as f32;
+                let s = tones
+                    .iter()
+                    .zip(&mut self.phases)
+                    .map(|(&hz, phase)| sine(phase, hz))
+                    .sum::<f32>();
+                (s * level, s * level)
+            }
+            TestSignal::Phase { hz } => {
+                const TURN_SECONDS: f32 = 8.0;
+                let offset = within(TURN_SECONDS) / TURN_SECONDS;
+                let right = 0.5 * ((self.phases[0] + offset) * TAU).sin();
+                let left = 0.5 * sine(&mut self.phases[0], *hz);
+                (left, right)
+            }
+        }
+    }
+}
+
+/// Sine at `phase` (in cycles), then advances the phase by one sample at `hz`.
+fn sine(phase: &mut f32, hz: f32) -> f32 {
+    let s = (*phase * TAU).sin();
+    *phase = (*phase + hz / SAMPLE_RATE as f32).fract();
+    s
+}
+
+/// A running generator thread; it stops when this is dropped.
+pub struct Generator {
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Generator {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Starts feeding `signal` into `samples` at [`SAMPLE_RATE`], in real time.
+pub fn spawn(
+    signal: TestSignal,
+    samples: Arc<Mutex<Samples>>,
+) -> Result<(Generator, cpal::StreamConfig)> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let config = cpal::StreamConfig {
+        channels: 2,
+        sample_rate: SAMPLE_RATE,
+        buffer_size: cpal::BufferSize::Default,
+    };
+
+    let stopped = stop.clone();
+    thread::Builder::new()
+        .name("test-signal".into())
+        .spawn(move || {
+            let mut synth = Synth::new(signal);
+            let mut buffer = Vec::new();
+            let started = Instant::now();
+            let mut written = 0u64;
+            while !stopped.load(Ordering::SeqCst) {
+                let due = (started.elapsed().as_secs_f64() * SAMPLE_RATE as f64) as u64;
+                buffer.clear();
+                for _ in written..due {
+                    let (left, right) = synth.next();
+                    buffer.extend([left, right]);
+                }
+                written = due;
+                push_frames(&samples, &buffer, 2);
+                thread::sleep(Duration::from_millis(5));
+            }
+        })?;
+
+    Ok((Generator { stop }, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{compute_spectrum, hop_size, Analyzer, FFT_SIZE};
+
+    #[test]
+    fn parses_signals_and_arguments() {
+        assert_eq!(TestSignal::parse("click").unwrap(), TestSignal::Click { bpm: 120.0 });
+        assert_eq!(TestSignal::parse("click:128").unwrap(), TestSignal::Click { bpm: 128.0 });
+        assert_eq!(TestSignal::parse("sweep:5").unwrap(), TestSignal::Sweep { seconds: 5.0 });
+        assert_eq!(TestSignal::parse("pink").unwrap(), TestSignal::PinkNoise);
+        assert_eq!(
+            TestSignal::parse("chord:220, 330").unwrap(),
+            TestSignal::Chord(vec![220.0, 330.0])
+        );
+
+        for bad in ["click:0", "click:-5", "click:fast", "click:inf", "chord:220,x", "square"] {
+            assert!(TestSignal::parse(bad).is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn click_track_is_detected_at_its_tempo() {
+        let mut synth = Synth::new(TestSignal::Click { bpm: 120.0 });
+        let mut analyzer = Analyzer::new();
+        let hop = hop_size(SAMPLE_RATE);
+        let mut samples = Vec::new();
+        let mut beat_info = None;
+
+        // Ten seconds of audio, analyzed a hop at a time as the live worker does.
+        while samples.len() < 10 * SAMPLE_RATE as usize {
+            samples.extend((0..hop).map(|_| {
+                let (left, right) = synth.next();
+                (left + right) / 2.0
+            }));
+            let window = &samples[samples.len().saturating_sub(FFT_SIZE)..];
+            let time = Duration::from_secs_f64(samples.len() as f64 / SAMPLE_RATE as f64);
+            beat_info = Some(analyzer.update(compute_spectrum(window, SAMPLE_RATE).as_ref(), time));
+        }
+
+        let beat_info = beat_info.unwrap();
+        // Onsets land on hop boundaries, about 23 ms apart, so intervals come out a
+        // little either side of 500 ms.
+        assert!((beat_info.bpm - 120.0).abs() < 4.0, "detected {} BPM", beat_info.bpm);
+        assert!((17..=21).contains(&beat_info.total_beats), "{} beats", beat_info.total_beats);
+    }
+}
@@ -0,0 +1,440 @@
+pub mod generator;
+pub mod pcm;
+pub mod watcher;
+
+use crate::{config::AudioConfig, metrics::METRICS};
+use anyhow::{anyhow, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use generator::{Generator, TestSignal};
+use pcm::{PcmReader, PcmSource, PcmSpec};
+use std::{
+    fmt,
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
+};
+use watcher::DeviceWatcher;
+
+/// Samples kept for analysis; older ones are dropped as new audio arrives.
+const MAX_SAMPLES: usize = 4096;
+
+/// Where audio comes from, chosen with `--input`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputSource {
+    /// Whatever is playing on an output device: the default one, or the first whose
+    /// name contains `name`.
+    Device { name: Option<String> },
+    /// A built-in test signal.
+    Test(TestSignal),
+    /// Raw PCM from stdin or a FIFO.
+    Pcm { source: PcmSource, spec: PcmSpec },
+}
+
+impl InputSource {
+    /// Parses `device[:<name>]`, `test:<kind>[:<arg>]`, `stdin` or `fifo:<path>`. Raw PCM
+    /// sources start out with the default [`PcmSpec`].
+    pub fn parse(s: &str) -> Result<Self> {
+        let pcm = |source| InputSource::Pcm {
+            source,
+            spec: PcmSpec::default(),
+        };
+        match s.split_once(':') {
+            _ if s == "device" => Ok(InputSource::Device { name: None }),
+            Some(("device", name)) if !name.is_empty() => Ok(InputSource::Device {
+                name: Some(name.to_string()),
+            }),
+            _ if s == "stdin" => Ok(pcm(PcmSource::Stdin)),
+            Some(("test", spec)) => Ok(InputSource::Test(TestSignal::parse(spec)?)),
+            Some(("fifo", path)) if !path.is_empty() => {
+                if cfg!(unix) {
+                    Ok(pcm(PcmSource::Fifo(path.into())))
+                } else {
+                    Err(anyhow!("`fifo:` input needs named pipes, which are only available on Unix"))
+                }
+            }
+            _ => Err(anyhow!(
+                "unknown input '{}'; expected `device[:<name>]`, `test:<kind>`, `stdin` or `fifo:<path>`",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputSource::Device { name: None } => write!(f, "default output device"),
+            InputSource::Device { name: Some(name) } => write!(f, "output device '{}'", name),
+            InputSource::Test(signal) => write!(f, "test signal {:?}", signal),
+            InputSource::Pcm {
+                source: PcmSource::Stdin,
+                spec,
+            } => write!(f, "{:?} PCM on stdin", spec.format),
+            InputSource::Pcm {
+                source: PcmSource::Fifo(path),
+                spec,
+            } => write!(f, "{:?} PCM from {}", spec.format, path.display()),
+        }
+    }
+}
+
+/// Outcome of the last attempt to open the input, for diagnostics.
+static STATE: Mutex<Option<String>> = Mutex::new(None);
+
+/// What the audio input was last doing, or `None` if it was never started.
+pub fn state() -> Option<String> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// A running input. Capture stops when it is dropped.
+pub enum Capture {
+    Device { _stream: cpal::Stream },
+    Generator { _generator: Generator },
+    Pcm { _reader: PcmReader },
+}
+
+/// Mono audio shared between the input and the analysis thread.
+#[derive(Debug, Default)]
+pub struct Samples {
+    /// The most recent [`MAX_SAMPLES`] samples, oldest first.
+    pub recent: Vec<f32>,
+    /// Samples pushed since the input was created, so readers can tell which are new.
+    pub written: u64,
+    /// Sample rate of the stream currently writing.
+    pub rate: u32,
+}
+
+/// Mixes interleaved frames down to mono and appends them to `samples`, keeping only
+/// the most recent [`MAX_SAMPLES`].
+fn push_frames(samples: &Mutex<Samples>, data: &[f32], channels: usize) {
+    let mut samples = samples.lock().unwrap();
+    let before = samples.recent.len();
+    let s = &mut samples.recent;
+    if channels > 1 {
+        for frame in data.chunks_exact(channels) {
+            let mono: f32 = frame.iter().sum::<f32>() / channels as f32;
+            s.push(mono);
+        }
+    } else {
+        s.extend_from_slice(data);
+    }
+    let pushed = s.len() - before;
+
+    if s.len() > MAX_SAMPLES {
+        let keep = s.len() - MAX_SAMPLES;
+        s.drain(0..keep);
+    }
+    samples.written += pushed as u64;
+}
+
+/// An input that has just been opened.
+struct Opened {
+    /// Keeps the input running; dropped to close it.
+    _capture: Capture,
+    config: cpal::StreamConfig,
+    /// Device name, or a description of the source for the other inputs.
+    name: String,
+    /// Which device was opened, for a device input.
+    id: Option<cpal::DeviceId>,
+}
+
+/// Opens `source`. If the stream fails later, the error is stored in `stream_error`;
+/// buffer underruns and overruns are only counted.
+fn open(
+    source: &InputSource,
+    samples: Arc<Mutex<Samples>>,
+    stream_error: Arc<Mutex<Option<String>>>,
+) -> Result<Opened> {
+    let result = match source {
+        InputSource::Device { name } => setup_device_stream(name.as_deref(), samples, stream_error)
+            .map(|(stream, config, name, id)| Opened {
+                _capture: Capture::Device { _stream: stream },
+                config,
+                name,
+                id,
+            }),
+        InputSource::Test(signal) => {
+            generator::spawn(signal.clone(), samples).map(|(generator, config)| Opened {
+                _capture: Capture::Generator { _generator: generator },
+                config,
+                name: source.to_string(),
+                id: None,
+            })
+        }
+        InputSource::Pcm { source: pcm, spec } => {
+            pcm::spawn(pcm, *spec, samples).map(|(reader, config)| Opened {
+                _capture: Capture::Pcm { _reader: reader },
+                config,
+                name: source.to_string(),
+                id: None,
+            })
+        }
+    };
+
+    *STATE.lock().unwrap_or_else(|e| e.into_inner()) = Some(match &result {
+        Ok(opened) => format!(
+            "{} at {} Hz, {} channels",
+            opened.name, opened.config.sample_rate, opened.config.channels
+        ),
+        Err(e) => format!("{} unavailable: {:#}", source, e),
+    });
+    result
+}
+
+/// Name shown for `device`, falling back to a generic one.
+fn device_name(device: &cpal::Device) -> String {
+    device
+        .description()
+        .map(|d| d.name().to_string())
+        .unwrap_or_else(|_| "output device".to_string())
+}
+
+/// Captures the default output device, or the first whose name contains `pinned`.
+fn setup_device_stream(
+    pinned: Option<&str>,
+    samples: Arc<Mutex<Samples>>,
+    stream_error: Arc<Mutex<Option<String>>>,
+) -> Result<(cpal::Stream, cpal::StreamConfig, String, Option<cpal::DeviceId>)> {
+    let host = cpal::default_host();
+    let device = match pinned {
+        None => host
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("No output device found"))?,
+        Some(pinned) => host
+            .output_devices()?
+            .find(|device| device_name(device).contains(pinned))
+            .ok_or_else(|| anyhow!("no output device named '{}' found", pinned))?,
+    };
+    let name = device_name(&device);
+    let id = device.id().ok();
+
+    let config: cpal::StreamConfig = device.default_output_config()?.into();
+    let channels = config.channels as usize;
+
+    let stream = device.build_input_stream(
+        &config,
+        move |data: &[f32], _: &_| push_frames(&samples, data, channels),
+        move |err| {
+            // A glitch, not a broken stream: count it and carry on.
+            if matches!(err, cpal::StreamError::BufferUnderrun) {
+                METRICS.audio_overruns.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            *stream_error.lock().unwrap() = Some(err.to_string());
+        },
+        None,
+    )?;
+
+    stream.play()?;
+    Ok((stream, config, name, id))
+}
+
+/// First wait before reconnecting; doubles with each failed attempt.
+const MIN_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// How long an open stream must carry nothing but zeros to count as silent.
+const SILENT_AFTER: Duration = Duration::from_secs(5);
+/// Samples quieter than this count as silence.
+const SILENCE_LEVEL: f32 = 1e-4;
+
+/// What the input is doing, for the info panel.
+pub enum AudioStatus<'a> {
+    /// Open, with sound within the last few seconds.
+    Running { name: &'a str, config: &'a cpal::StreamConfig },
+    /// Open, but nothing but silence for this long.
+    Silent { name: &'a str, config: &'a cpal::StreamConfig, since: Duration },
+    /// Not open; the next attempt is due in `retry_in`.
+    Failed { error: &'a str, retries: u32, retry_in: Duration },
+}
+
+impl fmt::Display for AudioStatus<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AudioStatus::Running { name, config } => {
+                write!(f, "{} · {} Hz · {} ch", name, config.sample_rate, config.channels)
+            }
+            AudioStatus::Silent { name, config, since } => write!(
+                f,
+                "{} · {} Hz · {} ch · silent for {}s",
+                name,
+                config.sample_rate,
+                config.channels,
+                since.as_secs()
+            ),
+            AudioStatus::Failed { error, retries, retry_in } => write!(
+                f,
+                "{} · retry {} in {:.1}s",
+                error,
+                retries,
+                retry_in.as_secs_f32()
+            ),
+        }
+    }
+}
+
+/// Keeps an input open, reconnecting with exponential backoff when it fails or
+/// reports an error, and keeps track of what it is doing for display.
+///
+/// Device inputs also watch the device list: an unpinned input moves to the new
+/// default output when `follow_default` is set, and a device that disappears and
+/// comes back is reopened straight away instead of waiting out the backoff.
+pub struct AudioInput {
+    source: InputSource,
+    follow_default: bool,
+    watcher: Option<DeviceWatcher>,
+    samples: Arc<Mutex<Samples>>,
+    stream_error: Arc<Mutex<Option<String>>>,
+    opened: Option<Opened>,
+    /// Config of the last stream opened, kept while reconnecting.
+    config: cpal::StreamConfig,
+    /// The default device changed and the input hasn't moved to it yet.
+    pending_switch: bool,
+    last_error: String,
+    /// Failed attempts since the input was last open.
+    retries: u32,
+    next_attempt: Instant,
+    last_sound: Instant,
+}
+
+impl AudioInput {
+    /// Tries to open `source` straight away; if that fails, [`poll`](Self::poll)
+    /// keeps retrying. Only a bad `config` is an error.
+    pub fn new(source: &InputSource, config: &AudioConfig) -> Result<Self> {
+        let interval = Duration::try_from_secs_f32(config.watch_interval.max(0.1))
+            .map_err(|_| anyhow!("[audio] watch_interval must be a number of seconds"))?;
+        // Without the watcher the input still reconnects, just not as promptly.
+        let watcher = match source {
+            InputSource::Device { .. } => DeviceWatcher::spawn(interval).ok(),
+            _ => None,
+        };
+        let mut input = Self {
+            source: source.clone(),
+            follow_default: config.follow_default,
+            watcher,
+            samples: Arc::new(Mutex::new(Samples::default())),
+            stream_error: Arc::new(Mutex::new(None)),
+            opened: None,
+            config: cpal::StreamConfig {
+                channels: 1,
+                sample_rate: 44100,
+                buffer_size: cpal::BufferSize::Default,
+            },
+            pending_switch: false,
+            last_error: String::new(),
+            retries: 0,
+            next_attempt: Instant::now(),
+            last_sound: Instant::now(),
+        };
+        input.poll();
+        Ok(input)
+    }
+
+    /// Reopens the input if it is down and the backoff has passed, and updates
+    /// silence tracking. Call once per frame.
+    pub fn poll(&mut self) {
+        if let Some(error) = self.stream_error.lock().unwrap().take() {
+            // Drop the broken stream first so the device is free to reopen.
+            self.opened = None;
+            self.last_error = error;
+            METRICS.stream_restarts.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let now = Instant::now();
+        if let Some(devices) = self.watcher.as_ref().and_then(DeviceWatcher::changed) {
+            match &self.opened {
+                None => self.next_attempt = now,
+                Some(opened)
+                    if self.follow_default
+                        && self.source == (InputSource::Device { name: None })
+                        && devices.default.is_some()
+                        && devices.default != opened.id =>
+                {
+                    self.pending_switch = true;
+                    self.next_attempt = now;
+                }
+                // The default is back on the device already open.
+                Some(_) => self.pending_switch = false,
+            }
+        }
+
+        if self.pending_switch && self.opened.is_some() && now >= self.next_attempt {
+            self.switch_device(now);
+        }
+
+        if self.opened.is_none() && now >= self.next_attempt {
+            match open(&self.source, self.samples.clone(), self.stream_error.clone()) {
+                Ok(opened) => {
+                    self.config = opened.config.clone();
+                    self.opened = Some(opened);
+                    self.pending_switch = false;
+                    self.retries = 0;
+                    self.last_sound = now;
+                    let mut samples = self.samples.lock().unwrap();
+                    samples.recent.clear();
+                    samples.rate = self.config.sample_rate;
+                }
+                Err(e) => self.failed(e, now),
+            }
+        }
+
+        if self.opened.is_some()
+            && self.samples.lock().unwrap().recent.iter().any(|s| s.abs() > SILENCE_LEVEL)
+        {
+            self.last_sound = now;
+        }
+    }
+
+    /// Moves to the current default device, keeping the samples already captured so
+    /// the display carries on. The old stream is closed first, as some backends won't
+    /// open a second one alongside it; if the new device won't open, the input is down
+    /// and reconnects with backoff like after any other failure.
+    fn switch_device(&mut self, now: Instant) {
+        self.opened = None;
+        // Whatever the old stream reported last no longer applies.
+        self.stream_error.lock().unwrap().take();
+        match open(&self.source, self.samples.clone(), self.stream_error.clone()) {
+            Ok(opened) => {
+                self.config = opened.config.clone();
+                self.opened = Some(opened);
+                self.pending_switch = false;
+                self.retries = 0;
+                self.samples.lock().unwrap().rate = self.config.sample_rate;
+            }
+            Err(e) => {
+                self.pending_switch = false;
+                self.failed(e, now);
+            }
+        }
+    }
+
+    /// Records a failed attempt to open the input and schedules the next one.
+    fn failed(&mut self, error: anyhow::Error, now: Instant) {
+        self.last_error = format!("{:#}", error);
+        self.retries += 1;
+        let backoff = MIN_BACKOFF.saturating_mul(1 << self.retries.min(16)) / 2;
+        self.next_attempt = now + backoff.min(MAX_BACKOFF);
+    }
+
+    /// The shared buffer the input writes mono samples to.
+    pub fn samples(&self) -> &Arc<Mutex<Samples>> {
+        &self.samples
+    }
+
+    pub fn status(&self) -> AudioStatus<'_> {
+        match &self.opened {
+            Some(opened) if self.last_sound.elapsed() >= SILENT_AFTER => AudioStatus::Silent {
+                name: &opened.name,
+                config: &opened.config,
+                since: self.last_sound.elapsed(),
+            },
+            Some(opened) => AudioStatus::Running {
+                name: &opened.name,
+                config: &opened.config,
+            },
+            None => AudioStatus::Failed {
+                error: &self.last_error,
+                retries: self.retries,
+                retry_in: self.next_attempt.saturating_duration_since(Instant::now()),
+            },
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Raw interleaved PCM read from stdin or a named pipe, e.g. from `ffmpeg -f s16le -`,
+//! `parec`, `sox -t raw` or MPD's fifo output.
+
+use super::{push_frames, Samples};
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Sample encodings accepted by `--pcm-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PcmFormat {
+    #[value(name = "u8")]
+    U8,
+    #[value(name = "s16le")]
+    S16Le,
+    #[value(name = "s16be")]
+    S16Be,
+    #[value(name = "s24le")]
+    S24Le,
+    #[value(name = "s32le")]
+    S32Le,
+    #[value(name = "f32le")]
+    F32Le,
+}
+
+impl PcmFormat {
+    fn bytes(self) -> usize {
+        match self {
+            PcmFormat::U8 => 1,
+            PcmFormat::S16Le | PcmFormat::S16Be => 2,
+            PcmFormat::S24Le => 3,
+            PcmFormat::S32Le | PcmFormat::F32Le => 4,
+        }
+    }
+
+    /// Decodes one sample to -1..1.
+    fn decode(self, b: &[u8]) -> f32 {
+        match self {
+            PcmFormat::U8 => (b[0] as f32 - 128.0) / 128.0,
+            PcmFormat::S16Le => i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+            PcmFormat::S16Be => i16::from_be_bytes([b[0], b[1]]) as f32 / 32768.0,
+            // Shift into the top of an i32 so the sign comes along.
+            PcmFormat::S24Le => i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2147483648.0,
+            PcmFormat::S32Le => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0,
+            PcmFormat::F32Le => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        }
+    }
+}
+
+/// Layout of the incoming stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PcmSpec {
+    pub format: PcmFormat,
+    pub rate: u32,
+    pub channels: u16,
+}
+
+impl Default for PcmSpec {
+    /// CD audio, which is also what MPD's fifo output writes unless told otherwise.
+    fn default() -> Self {
+        Self {
+            format: PcmFormat::S16Le,
+            rate: 44100,
+            channels: 2,
+        }
+    }
+}
+
+/// Where the stream is read from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PcmSource {
+    Stdin,
+    /// A named pipe, reopened whenever the writer closes it.
+    Fifo(PathBuf),
+}
+
+/// If no data arrives for this long, the writer is taken to be paused and silence
+/// is fed instead, so the display doesn't freeze on the last thing played.
+const STALL: Duration = Duration::from_millis(200);
+
+/// Running reader threads; they stop when this is dropped.
+pub struct PcmReader {
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for PcmReader {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+pub fn spawn(
+    source: &PcmSource,
+    spec: PcmSpec,
+    samples: Arc<Mutex<Samples>>,
+) -> Result<(PcmReader, cpal::StreamConfig)> {
+    if spec.rate == 0 || spec.channels == 0 {
+        return Err(anyhow!("--pcm-rate and --pcm-channels must be at least 1"));
+    }
+    if let PcmSource::Fifo(path) = source {
+        let file_type = fs::metadata(path)
+            .with_context(|| format!("cannot read {}", path.display()))?
+            .file_type();
+        if !is_fifo(file_type) {
+            return Err(anyhow!(
+                "{} is not a FIFO; create one with mkfifo",
+                path.display()
+            ));
+        }
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let started = Instant::now();
+    // Milliseconds since `started` at which data last arrived; MAX until the first.
+    let last_data = Arc::new(AtomicU64::new(u64::MAX));
+
+    let (stopped, last_read, shared) = (stop.clone(), last_data.clone(), samples.clone());
+    let source = source.clone();
+    thread::Builder::new()
+        .name("pcm-input".into())
+        .spawn(move || {
+            let mut on_data = |data: &[f32]| {
+                last_read.store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+                push_frames(&shared, data, spec.channels as usize);
+                !stopped.load(Ordering::SeqCst)
+            };
+            match &source {
+                PcmSource::Stdin => {
+                    let _ = read_stream(io::stdin().lock(), spec, &mut on_data);
+                }
+                PcmSource::Fifo(path) => {
+                    // Opening blocks until a writer shows up; EOF means it went away.
+                    while let Ok(true) = open_and_read(path, spec, &mut on_data) {}
+                }
+            }
+        })?;
+
+    let (stopped, frames_per_tick) = (stop.clone(), spec.rate as usize / 20);
+    thread::Builder::new()
+        .name("pcm-silence".into())
+        .spawn(move || {
+            let silence = vec![0.0; frames_per_tick];
+            while !stopped.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(50));
+                let now = started.elapsed().as_millis() as u64;
+                let idle = now.saturating_sub(last_data.load(Ordering::Relaxed));
+                if idle >= STALL.as_millis() as u64 {
+                    push_frames(&samples, &silence, 1);
+                }
+            }
+        })?;
+
+    let config = cpal::StreamConfig {
+        channels: spec.channels,
+        sample_rate: spec.rate,
+        buffer_size: cpal::BufferSize::Default,
+    };
+    Ok((PcmReader { stop }, config))
+}
+
+#[cfg(unix)]
+fn is_fifo(file_type: fs::FileType) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    file_type.is_fifo()
+}
+
+/// Named pipes in the Unix sense don't exist elsewhere; `fifo:` is refused when the
+/// input is parsed, so this is only reached if a `PcmSource::Fifo` is built by hand.
+#[cfg(not(unix))]
+fn is_fifo(_file_type: fs::FileType) -> bool {
+    false
+}
+
+/// Reads one writer's worth of data from the FIFO. Returns whether to keep going.
+fn open_and_read(
+    path: &Path,
+    spec: PcmSpec,
+    on_data: &mut impl FnMut(&[f32]) -> bool,
+) -> io::Result<bool> {
+    read_stream(File::open(path)?, spec, on_data)
+}
+
+/// Decodes `reader` until EOF, handing each batch of samples to `on_data`.
+/// Returns false if `on_data` asked to stop.
+fn read_stream(
+    mut reader: impl Read,
+    spec: PcmSpec,
+    on_data: &mut impl FnMut(&[f32]) -> bool,
+) -> io::Result<bool> {
+    let frame_bytes = spec.format.bytes() * spec.channels as usize;
+    let mut buffer = vec![0u8; frame_bytes * 1024];
+    let mut filled = 0;
+    let mut decoded = Vec::with_capacity(spec.channels as usize * 1024);
+
+    loop {
+        let n = match reader.read(&mut buffer[filled..]) {
+            Ok(0) => return Ok(true),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        filled += n;
+
+        // Only whole frames are decoded; a partial one waits for the next read.
+        let whole = filled - filled % frame_bytes;
+        decoded.clear();
+        decoded.extend(
+            buffer[..whole]
+                .chunks_exact(spec.format.bytes())
+                .map(|sample| spec.format.decode(sample)),
+        );
+        buffer.copy_within(whole..filled, 0);
+        filled -= whole;
+
+        if !decoded.is_empty() && !on_data(&decoded) {
+            return Ok(false);
+        }
+    }
+}
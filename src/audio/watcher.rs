@@ -0,0 +1,78 @@
+//! Background scan of the output devices, so the input can follow a change of
+//! default device and pick a pinned device back up when it is plugged in again.
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait},
+    DeviceId,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// The output devices as of one scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Devices {
+    pub default: Option<DeviceId>,
+    pub all: Vec<DeviceId>,
+}
+
+impl Devices {
+    fn scan() -> Self {
+        let host = cpal::default_host();
+        let default = host.default_output_device().and_then(|d| d.id().ok());
+        let all = host
+            .output_devices()
+            .map(|devices| devices.filter_map(|d| d.id().ok()).collect())
+            .unwrap_or_default();
+        Self { default, all }
+    }
+}
+
+/// A running scan thread; it stops when this is dropped.
+pub struct DeviceWatcher {
+    changes: Receiver<Devices>,
+    stop: Arc<AtomicBool>,
+}
+
+impl DeviceWatcher {
+    /// Scans every `interval`, reporting the device list whenever it differs from
+    /// the previous scan. The first scan is always reported.
+    pub fn spawn(interval: Duration) -> std::io::Result<Self> {
+        let (tx, changes) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        thread::Builder::new()
+            .name("device-watcher".into())
+            .spawn(move || {
+                let mut last = None;
+                while !stopped.load(Ordering::SeqCst) {
+                    let devices = Devices::scan();
+                    if last.as_ref() != Some(&devices) {
+                        if tx.send(devices.clone()).is_err() {
+                            return;
+                        }
+                        last = Some(devices);
+                    }
+                    thread::sleep(interval);
+                }
+            })?;
+        Ok(Self { changes, stop })
+    }
+
+    /// The latest device list, if it has changed since the last call.
+    pub fn changed(&self) -> Option<Devices> {
+        self.changes.try_iter().last()
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::audio::{
+    pcm::{PcmFormat, PcmSpec},
+    InputSource,
+};
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use std::{path::PathBuf, time::Duration};
+
+/// Terminal music visualizer driven by the system audio output.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Config file to use instead of the default location.
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Render without a terminal and save one frame to FILE, then exit.
+    /// The format follows the extension: .txt, .ans, .html or .svg.
+    #[arg(long, value_name = "FILE")]
+    pub screenshot: Option<PathBuf>,
+
+    /// Record the session to FILE in asciicast v2 format, for `asciinema play`.
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Render AUDIO offline (WAV, FLAC, MP3 or Ogg Vorbis) with the chosen visualizer
+    /// and write the frames to --output, then exit.
+    #[arg(long, value_name = "AUDIO", requires = "output")]
+    pub render: Option<PathBuf>,
+
+    /// Where --render writes: a `.gif` file, or a directory for a PNG sequence.
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Frame rate of --render output.
+    #[arg(long, default_value_t = 30)]
+    pub fps: u32,
+
+    /// Run without the terminal UI, only feeding the outputs enabled in the config
+    /// (OSC, WebSocket, DMX, WLED) until interrupted.
+    #[arg(long)]
+    pub no_tui: bool,
+
+    /// Size of the headless canvas in cells, e.g. `120x40`.
+    #[arg(long, value_name = "WxH", default_value = "120x40", value_parser = parse_size)]
+    pub size: (u16, u16),
+
+    /// Audio source: `device` for whatever is playing on the default output,
+    /// `device:<name>` to stay on the output device whose name contains `<name>`,
+    /// `test:<kind>` for a built-in signal (`sweep[:SECS]`, `white`, `pink`,
+    /// `click[:BPM]`, `chord[:HZ,HZ,...]` or `phase[:HZ]`), or raw PCM from `stdin`
+    /// or `fifo:<path>` (Unix only).
+    #[arg(long, value_name = "SOURCE", default_value = "device", value_parser = InputSource::parse)]
+    pub input: InputSource,
+
+    /// Sample format of raw PCM input.
+    #[arg(long, value_name = "FORMAT", default_value = "s16le")]
+    pub pcm_format: PcmFormat,
+
+    /// Sample rate of raw PCM input.
+    #[arg(long, value_name = "HZ", default_value_t = 44100)]
+    pub pcm_rate: u32,
+
+    /// Channel count of raw PCM input.
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    pub pcm_channels: u16,
+
+    /// Visualizer to start on (or to capture with --screenshot or --render).
+    #[arg(long, value_name = "NAME")]
+    pub visualizer: Option<String>,
+
+    /// Seconds of audio to analyze before a headless capture, so beat detection and
+    /// animated visualizers have settled.
+    #[arg(long, value_name = "SECS", default_value = "2", value_parser = parse_seconds)]
+    pub warmup: Duration,
+}
+
+impl Cli {
+    /// `--input`, with the `--pcm-*` settings applied to raw PCM sources.
+    pub fn input_source(&self) -> InputSource {
+        match &self.input {
+            InputSource::Pcm { source, .. } => InputSource::Pcm {
+                source: source.clone(),
+                spec: PcmSpec {
+                    format: self.pcm_format,
+                    rate: self.pcm_rate,
+                    channels: self.pcm_channels,
+                },
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+fn parse_size(s: &str) -> Result<(u16, u16)> {
+    let (w, h) = s
+        .split_once(['x', 'X'])
+        .ok_or_else(|| anyhow!("expected WIDTHxHEIGHT, e.g. 120x40"))?;
+    let (w, h): (u16, u16) = (w.trim().parse()?, h.trim().parse()?);
+    if w == 0 || h == 0 {
+        return Err(anyhow!("size must be at least 1x1"));
+    }
+    Ok((w, h))
+}
+
+fn parse_seconds(s: &str) -> Result<Duration> {
+    let seconds: f32 = s.trim().parse()?;
+    Duration::try_from_secs_f32(seconds)
+        .map_err(|_| anyhow!("expected a number of seconds, not '{}'", s))
+}
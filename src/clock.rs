@@ -0,0 +1,46 @@
+//! Time and randomness shared by beat detection and the animated visualizers.
+//!
+//! Live runs use the wall clock and an OS-seeded generator. Offline renders switch to
+//! a clock stepped once per frame and a fixed seed, so the same audio file always
+//! produces the same frames.
+
+use rand::{
+    distr::uniform::{SampleRange, SampleUniform},
+    rngs::SmallRng,
+    RngExt, SeedableRng,
+};
+use std::{
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+static START: OnceLock<Instant> = OnceLock::new();
+static STEPPED: Mutex<Option<Duration>> = Mutex::new(None);
+static RNG: Mutex<Option<SmallRng>> = Mutex::new(None);
+
+/// Time since the program started, or the stepped time once [`set_stepped`] is used.
+pub fn now() -> Duration {
+    let stepped = *STEPPED.lock().unwrap();
+    stepped.unwrap_or_else(|| START.get_or_init(Instant::now).elapsed())
+}
+
+/// Stops following the wall clock; [`now`] returns `t` until the next call.
+pub fn set_stepped(t: Duration) {
+    *STEPPED.lock().unwrap() = Some(t);
+}
+
+/// Restarts the random sequence from `seed`.
+pub fn seed(seed: u64) {
+    *RNG.lock().unwrap() = Some(SmallRng::seed_from_u64(seed));
+}
+
+/// Drop-in for `rand::random_range` that honours [`seed`].
+pub fn random_range<T, R>(range: R) -> T
+where
+    T: SampleUniform,
+    R: SampleRange<T>,
+{
+    let mut rng = RNG.lock().unwrap();
+    rng.get_or_insert_with(|| SmallRng::seed_from_u64(rand::random()))
+        .random_range(range)
+}
@@ -0,0 +1,476 @@
+use crate::{
+    output::{
+        dmx::{DmxFeature, DmxProtocol},
+        websocket::WebSocketFormat,
+        wled::{WledEffect, WledProtocol},
+    },
+    ui::{panes::LayoutPreset, transition::TransitionKind},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// User configuration, read from `config.toml`.
+///
+/// Every section is optional; anything left out falls back to the built-in defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Action name -> list of key strings, e.g. `quit = ["q", "ctrl+c"]`.
+    pub keys: HashMap<String, Vec<String>>,
+    pub playlist: PlaylistConfig,
+    pub transition: TransitionConfig,
+    pub layout: LayoutConfig,
+    pub markers: MarkersConfig,
+    pub export: ExportConfig,
+    pub osc: OscConfig,
+    pub websocket: WebSocketConfig,
+    pub dmx: DmxConfig,
+    pub wled: WledConfig,
+    pub control: ControlConfig,
+    pub metrics: MetricsConfig,
+    pub midi_out: MidiOutConfig,
+    pub midi_in: MidiInConfig,
+    pub tempo: TempoConfig,
+    pub audio: AudioConfig,
+}
+
+/// `[audio]`: how the capture device is chosen and watched.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    /// With `--input device`, move to the new default output when it changes.
+    pub follow_default: bool,
+    /// Seconds between scans of the device list.
+    pub watch_interval: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            follow_default: true,
+            watch_interval: 2.0,
+        }
+    }
+}
+
+/// `[tempo]`: manual tempo and the tempo lock.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TempoConfig {
+    /// While locked, let detected beats pull the phase back in line with the music.
+    pub resync: bool,
+}
+
+impl Default for TempoConfig {
+    fn default() -> Self {
+        Self { resync: true }
+    }
+}
+
+/// `[midi_in]`: MIDI controller input on an ALSA sequencer port.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MidiInConfig {
+    pub enabled: bool,
+    /// Client and port name other applications see.
+    pub port_name: String,
+    /// Connect on startup from the first device whose name contains this.
+    pub connect: Option<String>,
+    pub map: Vec<MidiMapping>,
+}
+
+impl Default for MidiInConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port_name: "music-visualizer-control".to_string(),
+            connect: None,
+            map: Vec::new(),
+        }
+    }
+}
+
+/// One `[[midi_in.map]]` entry: a note or controller and what it does.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MidiMapping {
+    pub note: Option<u8>,
+    pub cc: Option<u8>,
+    /// 1-16; any channel when left out.
+    pub channel: Option<u8>,
+    /// A `[keys]` action name, `sensitivity` for a controller to set it directly, or
+    /// `param:<name>` for a controller to set a parameter of the visualizer on show
+    /// (`param:decay` for the bars, `param:spin` for the radial orbit).
+    pub action: String,
+}
+
+/// `[midi_out]`: MIDI clock, beat notes and band controllers on an ALSA sequencer port.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MidiOutConfig {
+    pub enabled: bool,
+    /// Client and port name other applications see.
+    pub port_name: String,
+    /// 1-16.
+    pub channel: u8,
+    /// Send MIDI clock and start/stop at the detected tempo.
+    pub clock: bool,
+    /// Note played on each beat.
+    pub note: u8,
+    pub velocity: u8,
+    /// Seconds before the beat note is released.
+    pub note_length: f32,
+    /// Controller numbers for the sub, bass, mid and high bands.
+    pub band_cc: [u8; 4],
+}
+
+impl Default for MidiOutConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port_name: "music-visualizer".to_string(),
+            channel: 1,
+            clock: true,
+            note: 36,
+            velocity: 100,
+            note_length: 0.1,
+            band_cc: [20, 21, 22, 23],
+        }
+    }
+}
+
+/// `[metrics]`: Prometheus-style HTTP endpoint for monitoring unattended installs.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    /// Address to serve `GET /metrics` on.
+    pub bind: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:9898".to_string(),
+        }
+    }
+}
+
+/// `[control]`: Unix socket for scripting a running instance.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    /// Socket path; defaults to `$XDG_RUNTIME_DIR/music-visualizer.sock`.
+    pub path: Option<PathBuf>,
+}
+
+/// `[wled]`: LED strip or matrix output over WLED's realtime UDP protocols.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WledConfig {
+    pub enabled: bool,
+    /// WLED device as `host:port` (WLED listens on 21324).
+    pub target: String,
+    /// `auto`, `warls`, `drgb` or `dnrgb`.
+    pub protocol: WledProtocol,
+    /// Length of a strip. Ignored when `width` and `height` describe a matrix.
+    pub pixels: usize,
+    pub width: usize,
+    pub height: usize,
+    /// Matrix rows alternate direction, as most wired panels do.
+    pub serpentine: bool,
+    /// The first LED is at the end of the strip (or top of the matrix).
+    pub reverse: bool,
+    /// Upper limit on brightness, 0..1, to keep power draw in check.
+    pub brightness: f32,
+    /// `bars`, `spectrum` or `beat_flash`.
+    pub effect: WledEffect,
+    pub fps: f32,
+    /// Seconds WLED waits after the last packet before going back to its own effects.
+    pub timeout: u8,
+}
+
+impl Default for WledConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: "127.0.0.1:21324".to_string(),
+            protocol: WledProtocol::Auto,
+            pixels: 60,
+            width: 0,
+            height: 0,
+            serpentine: true,
+            reverse: false,
+            brightness: 0.5,
+            effect: WledEffect::Bars,
+            fps: 40.0,
+            timeout: 2,
+        }
+    }
+}
+
+/// `[dmx]`: Art-Net or sACN lighting output.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DmxConfig {
+    pub enabled: bool,
+    /// `artnet` or `sacn`.
+    pub protocol: DmxProtocol,
+    /// Receiver as `host:port`; defaults to Art-Net broadcast or the sACN multicast group.
+    pub target: Option<String>,
+    pub universe: u16,
+    /// Packets per second (at most 44, the DMX maximum).
+    pub refresh_hz: f32,
+    /// Seconds for a `beat` channel to fade from full to zero.
+    pub beat_decay: f32,
+    pub channels: Vec<DmxChannelConfig>,
+}
+
+impl Default for DmxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            protocol: DmxProtocol::ArtNet,
+            target: None,
+            universe: 0,
+            refresh_hz: 40.0,
+            beat_decay: 0.25,
+            channels: Vec::new(),
+        }
+    }
+}
+
+/// One `[[dmx.channels]]` mapping from an analysis feature to a DMX channel.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DmxChannelConfig {
+    /// 1-512.
+    pub channel: u16,
+    /// `beat`, `sub`, `bass`, `mid`, `high`, `note_hue`, `note_red`, `note_green` or `note_blue`.
+    pub feature: DmxFeature,
+    /// Multiplier applied to the 0..1 feature value before clamping.
+    #[serde(default = "default_gain")]
+    pub gain: f32,
+    /// DMX value sent for a feature value of 0.
+    #[serde(default)]
+    pub min: u8,
+    /// DMX value sent for a feature value of 1.
+    #[serde(default = "default_max")]
+    pub max: u8,
+}
+
+fn default_gain() -> f32 {
+    1.0
+}
+
+fn default_max() -> u8 {
+    255
+}
+
+/// `[websocket]`: local server streaming analysis frames to browser overlays.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebSocketConfig {
+    pub enabled: bool,
+    /// Address to listen on, as `host:port`.
+    pub bind: String,
+    /// `json` or `binary`.
+    pub format: WebSocketFormat,
+    /// Number of log-spaced spectrum bins per frame.
+    pub spectrum_bins: usize,
+    /// Frames buffered per client before that client starts skipping frames.
+    pub queue: usize,    /// Clients served at once; further connections are refused.
+    pub max_clients: usize,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:8765".to_string(),
+            format: WebSocketFormat::Json,
+            spectrum_bins: 64,
+            queue: 4,
+            max_clients: 16,
+        }
+    }
+}
+
+/// `[osc]`: analysis published as OSC messages over UDP.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OscConfig {
+    pub enabled: bool,
+    /// Receiver as `host:port`.
+    pub target: String,
+    /// Number of log-spaced bins in each `/spectrum` message; 0 turns it off.
+    pub spectrum_bins: usize,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: "127.0.0.1:9000".to_string(),
+            spectrum_bins: 32,
+        }
+    }
+}
+
+/// `[export]`: where screenshots taken with the screenshot key are written.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    pub dir: PathBuf,
+    /// Any of `txt`, `ansi`, `html` and `svg`; one file is written per format.
+    pub formats: Vec<String>,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("."),
+            formats: vec!["txt".into(), "ansi".into(), "html".into(), "svg".into()],
+        }
+    }
+}
+
+/// `[markers]`: canvas marker used to draw the visualizers.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkersConfig {
+    /// One of `braille`, `halfblock`, `quadrant`, `sextant`, `octant`, `block`, `dot` or `bar`.
+    pub default: String,
+    /// Visualizer name -> marker, overriding the default for that visualizer.
+    pub visualizers: HashMap<String, String>,
+}
+
+impl Default for MarkersConfig {
+    fn default() -> Self {
+        Self {
+            default: "braille".to_string(),
+            visualizers: HashMap::new(),
+        }
+    }
+}
+
+/// `[layout]`: how the screen is split into visualizer panes.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// One of `single`, `2-up`, `2-stack`, `4-up`, `main-sidebar` or `top-split`.
+    pub preset: LayoutPreset,
+    /// Visualizer names each pane starts on, in pane order.
+    pub panes: Vec<String>,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            preset: LayoutPreset::Single,
+            panes: Vec::new(),
+        }
+    }
+}
+
+/// `[transition]`: effect used when switching visualizers.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransitionConfig {
+    /// One of `cut`, `wipe`, `dissolve` or `slide`.
+    pub kind: TransitionKind,
+    pub seconds: f32,
+}
+
+impl Default for TransitionConfig {
+    fn default() -> Self {
+        Self {
+            kind: TransitionKind::Dissolve,
+            seconds: 0.6,
+        }
+    }
+}
+
+/// `[playlist]`: automatic rotation through visualizers.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlaylistConfig {
+    /// Start with auto-cycling switched on.
+    pub enabled: bool,
+    /// Shuffle the order, reshuffling after every full pass.
+    pub shuffle: bool,
+    /// Default time on each entry.
+    pub seconds: f32,
+    /// Default length of each entry in 4/4 bars of detected beats; overrides `seconds`.
+    pub bars: Option<u32>,
+    /// Hold time-based switches until the next detected beat.
+    pub sync_to_beat: bool,
+    /// How long to wait for that beat before switching anyway.
+    pub beat_grace_seconds: f32,
+    /// Visualizer names, either plain strings or `{ name, seconds, bars }` tables.
+    /// Empty means every visualizer in order.
+    pub entries: Vec<PlaylistEntryConfig>,
+}
+
+impl Default for PlaylistConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            shuffle: false,
+            seconds: 30.0,
+            bars: None,
+            sync_to_beat: true,
+            beat_grace_seconds: 2.0,
+            entries: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PlaylistEntryConfig {
+    Name(String),
+    Detailed {
+        name: String,
+        seconds: Option<f32>,
+        bars: Option<u32>,
+    },
+}
+
+impl Config {
+    /// Loads the config from `path` if given, else `$MUSIC_VISUALIZER_CONFIG`, or from
+    /// `$XDG_CONFIG_HOME/music-visualizer/config.toml` (falling back to `~/.config`).
+    /// A missing default file is not an error.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        if let Some(path) = path {
+            return Self::load_from(path.to_path_buf());
+        }
+        if let Ok(path) = env::var("MUSIC_VISUALIZER_CONFIG") {
+            return Self::load_from(PathBuf::from(path));
+        }
+        match default_path() {
+            Some(path) if path.exists() => Self::load_from(path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn load_from(path: PathBuf) -> Result<Self> {
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("invalid config {}", path.display()))
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("music-visualizer").join("config.toml"))
+}
@@ -0,0 +1,418 @@
+use crate::config::ControlConfig;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// A request from a control client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Switch the focused pane to a visualizer, by name or 1-based number.
+    Visualizer(String),
+    Next,
+    Prev,
+    /// Show (`Some(true)`), hide or toggle (`None`) the info panel.
+    Info(Option<bool>),
+    /// Pause, resume or toggle (`None`).
+    Pause(Option<bool>),
+    /// Beat detection threshold multiplier.
+    Sensitivity(f32),
+    /// Recolor the visualization with a named palette; see [`Theme`](crate::ui::theme::Theme).
+    Theme(String),
+    Bpm,
+    Peak,
+    Status,
+}
+
+const HELP: &str = "commands: visualizer <name|number>, next, prev, info [on|off], \
+                    pause [on|off], sensitivity <value>, theme <name>, bpm, peak, status";
+
+// JSON-RPC 2.0 error codes.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// A valid command that couldn't be carried out.
+const SERVER_ERROR: i64 = -32000;
+
+/// A failed request: its JSON-RPC error code and the message for the client.
+#[derive(Debug, Clone, PartialEq)]
+struct Error {
+    code: i64,
+    message: String,
+}
+
+impl Error {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Where the reply to a request goes.
+#[derive(Debug, Clone, PartialEq)]
+enum ReplyTo {
+    /// A plain text line.
+    Plain,
+    /// A JSON-RPC response with this id.
+    Rpc(Value),
+    /// Nowhere: the request was a JSON-RPC notification.
+    Nobody,
+}
+
+fn parse_switch(arg: Option<&str>) -> Result<Option<bool>, String> {
+    match arg {
+        None | Some("toggle") => Ok(None),
+        Some("on" | "true" | "1") => Ok(Some(true)),
+        Some("off" | "false" | "0") => Ok(Some(false)),
+        Some(other) => Err(format!("expected on, off or toggle, got '{}'", other)),
+    }
+}
+
+/// `method` with its arguments as a command. `method` is matched case-insensitively.
+fn parse_command(method: &str, args: &[String]) -> Result<Command, Error> {
+    let method = method.to_ascii_lowercase();
+    let arg = args.first().map(|a| a.as_str());
+    let invalid = |message: String| Error::new(INVALID_PARAMS, message);
+    let required = |what: &str| {
+        arg.map(str::to_string)
+            .ok_or_else(|| invalid(format!("{} needs {}", method, what)))
+    };
+    Ok(match method.as_str() {
+        "visualizer" | "switch" => Command::Visualizer(required("a name or number")?),
+        "next" => Command::Next,
+        "prev" | "previous" => Command::Prev,
+        "info" => Command::Info(parse_switch(arg).map_err(invalid)?),
+        "pause" => Command::Pause(parse_switch(arg).map_err(invalid)?),
+        "resume" => Command::Pause(Some(false)),
+        "sensitivity" => Command::Sensitivity(
+            required("a value")?
+                .parse()
+                .map_err(|_| invalid("sensitivity must be a number".to_string()))?,
+        ),
+        "theme" => Command::Theme(required("a name")?),
+        "bpm" => Command::Bpm,
+        "peak" => Command::Peak,
+        "status" => Command::Status,
+        _ => {
+            return Err(Error::new(
+                METHOD_NOT_FOUND,
+                format!("unknown command '{}'; {}", method, HELP),
+            ));
+        }
+    })
+}
+
+/// Turns one request line into a command plus where its reply goes.
+///
+/// Plain lines look like `visualizer bars`. Lines starting with `{` are JSON-RPC 2.0
+/// requests such as `{"jsonrpc":"2.0","id":1,"method":"pause","params":["on"]}`;
+/// without an `id` they are notifications, which get no reply.
+fn parse_line(line: &str) -> (Result<Command, Error>, ReplyTo) {
+    if !line.starts_with('{') {
+        let mut words = line.split_whitespace();
+        let method = words.next().unwrap_or("");
+        let args: Vec<String> = words.map(str::to_string).collect();
+        return (parse_command(method, &args), ReplyTo::Plain);
+    }
+
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            let error = Error::new(PARSE_ERROR, format!("invalid JSON: {}", e));
+            return (Err(error), ReplyTo::Rpc(Value::Null));
+        }
+    };
+    let method = request.get("method").and_then(Value::as_str);
+    let reply_to = match request.get("id") {
+        Some(id) => ReplyTo::Rpc(id.clone()),
+        // Only a valid request can be a notification; anything else is answered.
+        None if method.is_some() => ReplyTo::Nobody,
+        None => ReplyTo::Rpc(Value::Null),
+    };
+    let Some(method) = method else {
+        let error = Error::new(INVALID_REQUEST, "missing method");
+        return (Err(error), reply_to);
+    };
+    let args = match request.get("params") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::Array(params)) => params
+            .iter()
+            .map(|p| match p {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect(),
+        Some(other) => vec![match other {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }],
+    };
+    (parse_command(method, &args), reply_to)
+}
+
+/// The reply line for `result`, or `None` if nothing should be sent back.
+fn format_reply(result: Result<Value, Error>, reply_to: ReplyTo) -> Option<String> {
+    Some(match (reply_to, result) {
+        (ReplyTo::Nobody, _) => return None,
+        (ReplyTo::Rpc(id), Ok(value)) => {
+            json!({ "jsonrpc": "2.0", "id": id, "result": value }).to_string()
+        }
+        (ReplyTo::Rpc(id), Err(error)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": error.code, "message": error.message },
+        })
+        .to_string(),
+        (ReplyTo::Plain, Ok(Value::String(s))) => format!("ok {}", s),
+        (ReplyTo::Plain, Ok(Value::Null)) => "ok".to_string(),
+        (ReplyTo::Plain, Ok(value)) => format!("ok {}", value),
+        (ReplyTo::Plain, Err(error)) => format!("error {}", error.message),
+    })
+}
+
+/// A command waiting for the main loop, with the channel its reply goes back on.
+pub struct Request {
+    pub command: Command,
+    reply: Sender<Result<Value, String>>,
+}
+
+impl Request {
+    pub fn reply(self, result: Result<Value, String>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+/// Local socket that scripts use to drive a running instance.
+///
+/// Each client connection is served on its own thread; commands are handed to the
+/// main loop, which applies them between frames and sends back the reply.
+pub struct ControlServer {
+    requests: Receiver<Request>,
+    /// Removes the socket file when the server goes away.
+    _socket: socket::Socket,
+}
+
+impl ControlServer {
+    /// Returns `None` when the control socket is switched off.
+    pub fn from_config(config: &ControlConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let (tx, requests) = mpsc::channel();
+        let socket = socket::listen(config.path.clone(), tx)?;
+        Ok(Some(Self {
+            requests,
+            _socket: socket,
+        }))
+    }
+
+    /// The next command waiting to be applied, if any.
+    pub fn try_recv(&self) -> Option<Request> {
+        self.requests.try_recv().ok()
+    }
+}
+
+#[cfg(unix)]
+mod socket {
+    use super::{format_reply, parse_line, Error, Request, SERVER_ERROR};
+    use anyhow::{anyhow, Context, Result};
+    use std::{
+        fs::{self, Permissions},
+        io::{BufRead, BufReader, Write},
+        os::unix::{
+            fs::PermissionsExt,
+            net::{UnixListener, UnixStream},
+        },
+        path::PathBuf,
+        sync::mpsc::{self, Sender},
+        thread,
+    };
+
+    pub(super) struct Socket {
+        path: PathBuf,
+    }
+
+    impl Drop for Socket {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    pub(super) fn listen(path: Option<PathBuf>, requests: Sender<Request>) -> Result<Socket> {
+        let path = path.unwrap_or_else(default_path);
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(anyhow!(
+                    "control socket {} is in use by another instance",
+                    path.display()
+                ));
+            }
+            // Left behind by an instance that didn't shut down cleanly.
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove stale socket {}", path.display()))?;
+        }
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("failed to create control socket {}", path.display()))?;
+        let socket = Socket { path };
+        // Anyone who can connect can drive the instance, and the fallback location is
+        // the shared temporary directory.
+        fs::set_permissions(&socket.path, Permissions::from_mode(0o600)).with_context(|| {
+            format!("failed to restrict control socket {}", socket.path.display())
+        })?;
+
+        thread::Builder::new()
+            .name("control-accept".into())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let requests = requests.clone();
+                    let _ = thread::Builder::new()
+                        .name("control-client".into())
+                        .spawn(move || serve_client(stream, requests));
+                }
+            })?;
+
+        Ok(socket)
+    }
+
+    fn default_path() -> PathBuf {
+        match std::env::var_os("XDG_RUNTIME_DIR") {
+            Some(dir) => PathBuf::from(dir).join("music-visualizer.sock"),
+            None => std::env::temp_dir().join(format!(
+                "music-visualizer-{}.sock",
+                std::env::var("USER").unwrap_or_default()
+            )),
+        }
+    }
+
+    fn serve_client(stream: UnixStream, requests: Sender<Request>) {
+        let Ok(mut writer) = stream.try_clone() else {
+            return;
+        };
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (command, reply_to) = parse_line(line);
+            let result = match command {
+                Ok(command) => {
+                    let (reply, response) = mpsc::channel();
+                    if requests.send(Request { command, reply }).is_err() {
+                        return;
+                    }
+                    response
+                        .recv()
+                        .unwrap_or_else(|_| Err("shutting down".to_string()))
+                        .map_err(|message| Error::new(SERVER_ERROR, message))
+                }
+                Err(error) => Err(error),
+            };
+            if let Some(reply) = format_reply(result, reply_to)
+                && writeln!(writer, "{}", reply).is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod socket {
+    use super::Request;
+    use anyhow::{anyhow, Result};
+    use std::{path::PathBuf, sync::mpsc::Sender};
+
+    pub(super) struct Socket;
+
+    pub(super) fn listen(_path: Option<PathBuf>, _requests: Sender<Request>) -> Result<Socket> {
+        Err(anyhow!("the control socket needs Unix domain sockets, which this platform lacks"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(line: &str) -> i64 {
+        parse_line(line).0.unwrap_err().code
+    }
+
+    #[test]
+    fn plain_commands() {
+        let (command, reply_to) = parse_line("Visualizer bars");
+        assert_eq!(command, Ok(Command::Visualizer("bars".into())));
+        assert_eq!(reply_to, ReplyTo::Plain);
+        assert_eq!(parse_line("pause off").0, Ok(Command::Pause(Some(false))));
+        assert_eq!(parse_line("info").0, Ok(Command::Info(None)));
+        assert_eq!(parse_line("sensitivity 1.5").0, Ok(Command::Sensitivity(1.5)));
+
+        assert_eq!(code("sensitivity loud"), INVALID_PARAMS);
+        assert_eq!(code("theme"), INVALID_PARAMS);
+        assert_eq!(code("dance"), METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn json_rpc_requests() {
+        let (command, reply_to) =
+            parse_line(r#"{"jsonrpc":"2.0","id":7,"method":"PAUSE","params":["on"]}"#);
+        assert_eq!(command, Ok(Command::Pause(Some(true))));
+        assert_eq!(reply_to, ReplyTo::Rpc(json!(7)));
+        // A lone parameter needn't be wrapped in an array.
+        let line = r#"{"jsonrpc":"2.0","id":1,"method":"sensitivity","params":2}"#;
+        let (command, _) = parse_line(line);
+        assert_eq!(command, Ok(Command::Sensitivity(2.0)));
+
+        assert_eq!(code("{not json"), PARSE_ERROR);
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1}"#), INVALID_REQUEST);
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1,"method":"dance"}"#), METHOD_NOT_FOUND);
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1,"method":"theme"}"#), INVALID_PARAMS);
+    }
+
+    #[test]
+    fn notifications_get_no_reply() {
+        let (command, reply_to) = parse_line(r#"{"jsonrpc":"2.0","method":"next"}"#);
+        assert_eq!(command, Ok(Command::Next));
+        assert_eq!(reply_to, ReplyTo::Nobody);
+        assert_eq!(format_reply(Ok(json!("bars")), reply_to), None);
+
+        // Even a failed notification stays silent, but a request without a method
+        // can't be one and is answered.
+        let (command, reply_to) = parse_line(r#"{"jsonrpc":"2.0","method":"dance"}"#);
+        assert!(command.is_err());
+        assert_eq!(format_reply(command.map(|_| Value::Null), reply_to), None);
+        assert_eq!(parse_line(r#"{"jsonrpc":"2.0"}"#).1, ReplyTo::Rpc(Value::Null));
+    }
+
+    #[test]
+    fn replies() {
+        let error = Error::new(METHOD_NOT_FOUND, "unknown command 'dance'");
+        let reply = format_reply(Err(error.clone()), ReplyTo::Rpc(json!(3))).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&reply).unwrap(),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "error": { "code": -32601, "message": "unknown command 'dance'" },
+            })
+        );
+        let reply = format_reply(Ok(json!(120.0)), ReplyTo::Rpc(json!("a"))).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&reply).unwrap(),
+            json!({ "jsonrpc": "2.0", "id": "a", "result": 120.0 })
+        );
+
+        assert_eq!(format_reply(Ok(json!("bars")), ReplyTo::Plain).unwrap(), "ok bars");
+        assert_eq!(format_reply(Ok(Value::Null), ReplyTo::Plain).unwrap(), "ok");
+        assert_eq!(format_reply(Ok(json!(true)), ReplyTo::Plain).unwrap(), "ok true");
+        assert_eq!(
+            format_reply(Err(error), ReplyTo::Plain).unwrap(),
+            "error unknown command 'dance'"
+        );
+    }
+}
@@ -0,0 +1,503 @@
+use anyhow::{anyhow, Context, Result};
+use ratatui::{
+    buffer::{Buffer, Cell},
+    layout::Rect,
+    style::{Color, Modifier},
+    text::Span,
+};
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Output formats for screenshots of a rendered buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Plain text, no colors.
+    Text,
+    /// Text with ANSI SGR color escapes, for `cat` in a terminal.
+    Ansi,
+    /// Self-contained HTML page with a colored `<pre>` block.
+    Html,
+    /// SVG image; Braille cells are drawn as dots so they survive missing fonts.
+    Svg,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Ansi => "ans",
+            ExportFormat::Html => "html",
+            ExportFormat::Svg => "svg",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "txt" | "text" => Some(ExportFormat::Text),
+            "ans" | "ansi" => Some(ExportFormat::Ansi),
+            "html" | "htm" => Some(ExportFormat::Html),
+            "svg" => Some(ExportFormat::Svg),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_name)
+            .ok_or_else(|| {
+                anyhow!(
+                    "can't tell screenshot format from '{}' (use .txt, .ans, .html or .svg)",
+                    path.display()
+                )
+            })
+    }
+}
+
+/// Writes the `area` part of `buf` to `path` in the format given by its extension.
+pub fn save(buf: &Buffer, area: Rect, path: &Path) -> Result<()> {
+    let format = ExportFormat::from_path(path)?;
+    fs::write(path, render(buf, area, format))
+        .with_context(|| format!("failed to write screenshot {}", path.display()))
+}
+
+/// Writes one `music-visualizer-<unix time in ms>.<ext>` file per format in `formats`
+/// to `dir`. If those names are taken, a `-2`, `-3`, ... suffix is added rather than
+/// overwriting an earlier screenshot.
+pub fn save_all(buf: &Buffer, area: Rect, dir: &Path, formats: &[String]) -> Result<Vec<PathBuf>> {
+    let formats = formats
+        .iter()
+        .map(|name| {
+            ExportFormat::from_name(name)
+                .ok_or_else(|| anyhow!("unknown screenshot format '{}' in [export]", name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create screenshot directory {}", dir.display()))?;
+
+    let paths = |base: &str| -> Vec<PathBuf> {
+        formats
+            .iter()
+            .map(|format| dir.join(format!("{}.{}", base, format.extension())))
+            .collect()
+    };
+    let paths = (1..)
+        .map(|n| match n {
+            1 => paths(&format!("music-visualizer-{}", stamp)),
+            n => paths(&format!("music-visualizer-{}-{}", stamp, n)),
+        })
+        .find(|paths| paths.iter().all(|path| !path.exists()))
+        .expect("some suffix is free");
+
+    for (path, &format) in paths.iter().zip(&formats) {
+        fs::write(path, render(buf, area, format))
+            .with_context(|| format!("failed to write screenshot {}", path.display()))?;
+    }
+    Ok(paths)
+}
+
+pub fn render(buf: &Buffer, area: Rect, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Text => render_text(buf, area),
+        ExportFormat::Ansi => render_ansi(buf, area),
+        ExportFormat::Html => render_html(buf, area),
+        ExportFormat::Svg => render_svg(buf, area),
+    }
+}
+
+/// Cells of one row; the cells hidden behind the trailing half of a wide character
+/// are skipped.
+fn row(buf: &Buffer, area: Rect, y: u16) -> impl Iterator<Item = (u16, &Cell)> {
+    let mut hidden = 0;
+    (area.left()..area.right())
+        .map(move |x| (x - area.x, &buf[(x, y)]))
+        .filter(move |(_, cell)| {
+            if hidden > 0 {
+                hidden -= 1;
+                return false;
+            }
+            hidden = Span::raw(cell.symbol()).width().saturating_sub(1);
+            !cell.symbol().is_empty()
+        })
+}
+
+fn render_text(buf: &Buffer, area: Rect) -> String {
+    let mut out = String::new();
+    for y in area.top()..area.bottom() {
+        let line: String = row(buf, area, y).map(|(_, cell)| cell.symbol()).collect();
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn ansi_color(color: Color, background: bool) -> Option<String> {
+    let base = if background { 40 } else { 30 };
+    let code = match color {
+        Color::Reset => return None,
+        Color::Black => base,
+        Color::Red => base + 1,
+        Color::Green => base + 2,
+        Color::Yellow => base + 3,
+        Color::Blue => base + 4,
+        Color::Magenta => base + 5,
+        Color::Cyan => base + 6,
+        Color::Gray => base + 7,
+        Color::DarkGray => base + 60,
+        Color::LightRed => base + 61,
+        Color::LightGreen => base + 62,
+        Color::LightYellow => base + 63,
+        Color::LightBlue => base + 64,
+        Color::LightMagenta => base + 65,
+        Color::LightCyan => base + 66,
+        Color::White => base + 67,
+        Color::Indexed(i) => return Some(format!("{};5;{}", base + 8, i)),
+        Color::Rgb(r, g, b) => return Some(format!("{};2;{};{};{}", base + 8, r, g, b)),
+    };
+    Some(code.to_string())
+}
+
+fn render_ansi(buf: &Buffer, area: Rect) -> String {
+    let mut out = String::new();
+    for y in area.top()..area.bottom() {
+        let mut last: Option<(Color, Color, Modifier)> = None;
+        for (_, cell) in row(buf, area, y) {
+            let style = (cell.fg, cell.bg, cell.modifier);
+            if last != Some(style) {
+                let mut codes = vec!["0".to_string()];
+                if cell.modifier.contains(Modifier::BOLD) {
+                    codes.push("1".into());
+                }
+                if cell.modifier.contains(Modifier::DIM) {
+                    codes.push("2".into());
+                }
+                if cell.modifier.contains(Modifier::ITALIC) {
+                    codes.push("3".into());
+                }
+                if cell.modifier.contains(Modifier::UNDERLINED) {
+                    codes.push("4".into());
+                }
+                if cell.modifier.contains(Modifier::REVERSED) {
+                    codes.push("7".into());
+                }
+                codes.extend(ansi_color(cell.fg, false));
+                codes.extend(ansi_color(cell.bg, true));
+                let _ = write!(out, "\x1b[{}m", codes.join(";"));
+                last = Some(style);
+            }
+            out.push_str(cell.symbol());
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+/// An 8-bit RGB color.
+pub type Rgb = (u8, u8, u8);
+
+pub const DEFAULT_FG: Rgb = (0xe5, 0xe5, 0xe5);
+pub const DEFAULT_BG: Rgb = (0x0c, 0x0c, 0x0c);
+
+/// The 16 basic colors as xterm renders them by default.
+const BASIC_COLORS: [Rgb; 16] = [
+    (0x00, 0x00, 0x00),
+    (0xcd, 0x00, 0x00),
+    (0x00, 0xcd, 0x00),
+    (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee),
+    (0xcd, 0x00, 0xcd),
+    (0x00, 0xcd, 0xcd),
+    (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f),
+    (0xff, 0x00, 0x00),
+    (0x00, 0xff, 0x00),
+    (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff),
+    (0xff, 0x00, 0xff),
+    (0x00, 0xff, 0xff),
+    (0xff, 0xff, 0xff),
+];
+
+/// RGB value of a terminal color, or `None` for the terminal's default.
+pub fn rgb(color: Color) -> Option<Rgb> {
+    let index = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => return Some((r, g, b)),
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+        Color::Indexed(i) => i,
+    };
+    Some(match index {
+        0..=15 => BASIC_COLORS[index as usize],
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = index - 16;
+            (level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        _ => {
+            let v = 8 + (index - 232) * 10;
+            (v, v, v)
+        }
+    })
+}
+
+/// Foreground and background of a cell, with reverse video applied. The background is
+/// `None` where the terminal default shows through.
+pub fn cell_rgb(cell: &Cell) -> (Rgb, Option<Rgb>) {
+    let fg = rgb(cell.fg);
+    let bg = rgb(cell.bg);
+    if cell.modifier.contains(Modifier::REVERSED) {
+        (bg.unwrap_or(DEFAULT_BG), Some(fg.unwrap_or(DEFAULT_FG)))
+    } else {
+        (fg.unwrap_or(DEFAULT_FG), bg)
+    }
+}
+
+/// [`cell_rgb`] as `#rrggbb` strings.
+fn cell_colors(cell: &Cell) -> (String, Option<String>) {
+    let hex = |(r, g, b): (u8, u8, u8)| format!("#{:02x}{:02x}{:02x}", r, g, b);
+    let (fg, bg) = cell_rgb(cell);
+    (hex(fg), bg.map(hex))
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_html(buf: &Buffer, area: Rect) -> String {
+    let (r, g, b) = DEFAULT_BG;
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>music_visualizer screenshot</title>\n</head>\n\
+         <body style=\"margin:0;background:#{:02x}{:02x}{:02x}\">\n\
+         <pre style=\"margin:0;padding:8px;font-family:'DejaVu Sans Mono',Menlo,Consolas,monospace;font-size:14px;line-height:1.2\">",
+        r, g, b
+    );
+
+    for y in area.top()..area.bottom() {
+        let mut run = String::new();
+        let mut run_style: Option<(String, Option<String>, bool)> = None;
+        let flush = |out: &mut String, run: &mut String, style: &Option<(String, Option<String>, bool)>| {
+            if let Some((fg, bg, bold)) = style {
+                let mut css = format!("color:{}", fg);
+                if let Some(bg) = bg {
+                    let _ = write!(css, ";background:{}", bg);
+                }
+                if *bold {
+                    css.push_str(";font-weight:bold");
+                }
+                let _ = write!(out, "<span style=\"{}\">{}</span>", css, escape_xml(run));
+            }
+            run.clear();
+        };
+
+        for (_, cell) in row(buf, area, y) {
+            let (fg, bg) = cell_colors(cell);
+            let style = (fg, bg, cell.modifier.contains(Modifier::BOLD));
+            if run_style.as_ref() != Some(&style) {
+                flush(&mut out, &mut run, &run_style);
+                run_style = Some(style);
+            }
+            run.push_str(cell.symbol());
+        }
+        flush(&mut out, &mut run, &run_style);
+        out.push('\n');
+    }
+
+    out.push_str("</pre>\n</body>\n</html>\n");
+    out
+}
+
+const CELL_WIDTH: f32 = 8.4;
+const CELL_HEIGHT: f32 = 17.0;
+
+fn render_svg(buf: &Buffer, area: Rect) -> String {
+    let width = area.width as f32 * CELL_WIDTH;
+    let height = area.height as f32 * CELL_HEIGHT;
+    let (r, g, b) = DEFAULT_BG;
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"#{r:02x}{g:02x}{b:02x}\"/>\n\
+         <g font-family=\"'DejaVu Sans Mono',Menlo,Consolas,monospace\" font-size=\"14\" xml:space=\"preserve\">",
+        w = width,
+        h = height,
+        r = r,
+        g = g,
+        b = b
+    );
+
+    let dot_radius = (CELL_WIDTH / 2.0).min(CELL_HEIGHT / 4.0) * 0.38;
+
+    for y in area.top()..area.bottom() {
+        let top = (y - area.y) as f32 * CELL_HEIGHT;
+        for (col, cell) in row(buf, area, y) {
+            let left = col as f32 * CELL_WIDTH;
+            let (fg, bg) = cell_colors(cell);
+
+            if let Some(bg) = bg {
+                let _ = writeln!(
+                    out,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    left, top, CELL_WIDTH, CELL_HEIGHT, bg
+                );
+            }
+
+            let symbol = cell.symbol();
+            if symbol.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(bits) = braille_bits(symbol) {
+                // Braille dot layout: bits 0-2 and 6 are the left column, 3-5 and 7 the right.
+                const DOTS: [(u8, f32, f32); 8] = [
+                    (0x01, 0.0, 0.0),
+                    (0x02, 0.0, 1.0),
+                    (0x04, 0.0, 2.0),
+                    (0x08, 1.0, 0.0),
+                    (0x10, 1.0, 1.0),
+                    (0x20, 1.0, 2.0),
+                    (0x40, 0.0, 3.0),
+                    (0x80, 1.0, 3.0),
+                ];
+                for (bit, dx, dy) in DOTS {
+                    if bits & bit != 0 {
+                        let _ = writeln!(
+                            out,
+                            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\"/>",
+                            left + (dx + 0.5) * CELL_WIDTH / 2.0,
+                            top + (dy + 0.5) * CELL_HEIGHT / 4.0,
+                            dot_radius,
+                            fg
+                        );
+                    }
+                }
+            } else {
+                let weight = if cell.modifier.contains(Modifier::BOLD) {
+                    " font-weight=\"bold\""
+                } else {
+                    ""
+                };
+                let _ = writeln!(
+                    out,
+                    "<text x=\"{:.2}\" y=\"{:.2}\" fill=\"{}\"{}>{}</text>",
+                    left,
+                    top + CELL_HEIGHT * 0.78,
+                    fg,
+                    weight,
+                    escape_xml(symbol)
+                );
+            }
+        }
+    }
+
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+/// Dot bits of a Braille pattern character (U+2800..U+28FF).
+pub fn braille_bits(symbol: &str) -> Option<u8> {
+    let mut chars = symbol.chars();
+    let c = chars.next()?;
+    if chars.next().is_some() {
+        return None;
+    }
+    let code = c as u32;
+    (0x2800..=0x28FF).contains(&code).then(|| (code - 0x2800) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Style;
+
+    /// Two rows of three columns: markup characters in color on the first, and a
+    /// wide character followed by a bold Braille cell on the second.
+    fn sample() -> Buffer {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 3, 2));
+        buf.set_string(0, 0, "<&", Style::default().fg(Color::Red));
+        buf.set_string(2, 0, "\"", Style::default().fg(Color::Rgb(1, 2, 3)).bg(Color::Blue));
+        buf.set_string(0, 1, "界", Style::default());
+        buf.set_string(2, 1, "⠁", Style::default().fg(Color::Indexed(196)).add_modifier(Modifier::BOLD));
+        buf
+    }
+
+    fn render_sample(format: ExportFormat) -> String {
+        let buf = sample();
+        render(&buf, buf.area, format)
+    }
+
+    #[test]
+    fn text_drops_styles_and_wide_character_halves() {
+        assert_eq!(render_sample(ExportFormat::Text), "<&\"\n界⠁\n");
+    }
+
+    #[test]
+    fn ansi_switches_style_only_when_it_changes() {
+        assert_eq!(
+            render_sample(ExportFormat::Ansi),
+            "\x1b[0;31m<&\x1b[0;38;2;1;2;3;44m\"\x1b[0m\n\
+             \x1b[0m界\x1b[0;1;38;5;196m⠁\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn html_escapes_markup_and_groups_runs() {
+        let html = render_sample(ExportFormat::Html);
+        let start = html.find("line-height:1.2\">").unwrap() + "line-height:1.2\">".len();
+        let body = &html[start..html.find("</pre>").unwrap()];
+        assert_eq!(
+            body,
+            "<span style=\"color:#cd0000\">&lt;&amp;</span>\
+             <span style=\"color:#010203;background:#0000ee\">&quot;</span>\n\
+             <span style=\"color:#e5e5e5\">界</span>\
+             <span style=\"color:#ff0000;font-weight:bold\">⠁</span>\n"
+        );
+    }
+
+    #[test]
+    fn svg_places_cells_and_draws_braille_as_dots() {
+        let svg = render_sample(ExportFormat::Svg);
+        let cells: Vec<&str> = svg.lines().skip(3).collect();
+        assert_eq!(
+            cells,
+            [
+                "<text x=\"0.00\" y=\"13.26\" fill=\"#cd0000\">&lt;</text>",
+                "<text x=\"8.40\" y=\"13.26\" fill=\"#cd0000\">&amp;</text>",
+                "<rect x=\"16.8\" y=\"0\" width=\"8.4\" height=\"17\" fill=\"#0000ee\"/>",
+                "<text x=\"16.80\" y=\"13.26\" fill=\"#010203\">&quot;</text>",
+                "<text x=\"0.00\" y=\"30.26\" fill=\"#e5e5e5\">界</text>",
+                "<circle cx=\"18.90\" cy=\"19.12\" r=\"1.60\" fill=\"#ff0000\"/>",
+                "</g>",
+                "</svg>",
+            ]
+        );
+    }
+}
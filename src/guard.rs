@@ -0,0 +1,126 @@
+//! Puts the terminal back the way it was however the program ends: normally, with
+//! an error, on a panic, or on SIGINT/SIGTERM (and SIGHUP on Unix).
+
+use crate::{audio, metrics::METRICS};
+use anyhow::Result;
+use crossterm::{
+    cursor, execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+#[cfg(unix)]
+use signal_hook::consts::SIGHUP;
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    low_level,
+};
+use std::{
+    io::{self, Write},
+    panic, process,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Whether the terminal is currently in raw mode on the alternate screen.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+const TERMINATION_SIGNALS: &[i32] = &[SIGINT, SIGTERM, SIGHUP];
+#[cfg(not(unix))]
+const TERMINATION_SIGNALS: &[i32] = &[SIGINT, SIGTERM];
+
+/// Raw mode and the alternate screen for as long as this is alive.
+pub struct TerminalGuard {
+    /// Number of the last termination signal received, or 0.
+    signal: Arc<AtomicUsize>,
+}
+
+impl TerminalGuard {
+    pub fn enter(out: &mut impl Write) -> Result<Self> {
+        // Only a panic on this thread ends the program; one on a background thread
+        // (say, a WebSocket client) leaves the display running.
+        let main_thread = thread::current().id();
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if thread::current().id() == main_thread {
+                restore();
+            }
+            previous(info);
+            print_diagnostic();
+        }));
+
+        // The main loop notices the first signal and shuts down cleanly; a second one
+        // exits straight away in case the loop is stuck.
+        let signal = Arc::new(AtomicUsize::new(0));
+        let received = Arc::new(AtomicUsize::new(0));
+        for &sig in TERMINATION_SIGNALS {
+            let (signal, received) = (signal.clone(), received.clone());
+            // SAFETY: the handler only stores to atomics, which is async-signal-safe.
+            unsafe {
+                low_level::register(sig, move || {
+                    signal.store(sig as usize, Ordering::SeqCst);
+                    received.fetch_add(1, Ordering::SeqCst);
+                })?;
+            }
+        }
+        // Restoring the terminal isn't safe inside a signal handler, so the forced
+        // exit happens here instead.
+        thread::Builder::new()
+            .name("signal-watch".into())
+            .spawn(move || loop {
+                thread::sleep(Duration::from_millis(50));
+                if received.load(Ordering::SeqCst) >= 2 {
+                    restore();
+                    process::exit(1);
+                }
+            })?;
+
+        enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        execute!(out, EnterAlternateScreen)?;
+        Ok(Self { signal })
+    }
+
+    /// Name of the termination signal received, if any.
+    pub fn signal(&self) -> Option<&'static str> {
+        match self.signal.load(Ordering::SeqCst) as i32 {
+            0 => None,
+            SIGINT => Some("SIGINT"),
+            SIGTERM => Some("SIGTERM"),
+            #[cfg(unix)]
+            SIGHUP => Some("SIGHUP"),
+            _ => Some("a signal"),
+        }
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Leaves raw mode and the alternate screen, if still in them.
+fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, cursor::Show);
+    }
+}
+
+/// One line on stderr about the audio input, to go with an error or panic message.
+pub fn print_diagnostic() {
+    let Some(state) = audio::state() else {
+        return;
+    };
+    eprintln!(
+        "audio: {}; {} stream restarts, {} overruns, last input level {:.3}",
+        state,
+        METRICS.stream_restarts.load(Ordering::Relaxed),
+        METRICS.audio_overruns.load(Ordering::Relaxed),
+        METRICS.input_level.get(),
+    );
+}
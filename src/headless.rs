@@ -0,0 +1,157 @@
+use crate::{
+    analysis::{
+        get_peak_frequency,
+        worker::{AnalysisWorker, Snapshot},
+        Analyzer,
+    },
+    audio::{AudioInput, AudioStatus, InputSource},
+    config::AudioConfig,
+    control::{Command, ControlServer},
+    export,
+    keymap::Action,
+    midi_in::{MidiCommand, MidiInput},
+    output::Outputs,
+    ui::{markers::MarkerSettings, panes::Panes, DrawContext},
+};
+use anyhow::{anyhow, Result};
+use ratatui::{backend::TestBackend, Terminal};
+use serde_json::json;
+use std::{
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Captures live audio for `warmup`, drawing into an off-screen `width`x`height`
+/// terminal as the TUI would, then saves the last frame to `path`.
+pub fn screenshot(
+    path: &Path,
+    input: &InputSource,
+    audio_config: &AudioConfig,
+    (width, height): (u16, u16),
+    warmup: Duration,
+    panes: &mut Panes,
+    markers: &MarkerSettings,
+) -> Result<()> {
+    // Fail on a bad extension before spending the warmup time.
+    export::ExportFormat::from_path(path)?;
+
+    let mut audio = AudioInput::new(input, audio_config)?;
+    if let AudioStatus::Failed { error, .. } = audio.status() {
+        return Err(anyhow!("cannot open audio input: {}", error));
+    }
+
+    let worker =
+        AnalysisWorker::spawn(audio.samples().clone(), Analyzer::new(), Outputs::default())?;
+    let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+    let started = Instant::now();
+    let mut drawn = false;
+
+    loop {
+        audio.poll();
+        let Snapshot {
+            spectrum,
+            beat_info,
+        } = worker.take();
+
+        if let Some(spectrum) = &spectrum {
+            let ctx = DrawContext {
+                spectrum,
+                beat_info: &beat_info,
+                markers,
+            };
+            let mut draw_result = Ok(());
+            terminal.draw(|f| draw_result = panes.draw(f, f.area(), &ctx))?;
+            draw_result?;
+            drawn = true;
+        }
+
+        if started.elapsed() >= warmup && drawn {
+            break;
+        }
+        if started.elapsed() >= warmup + Duration::from_secs(10) {
+            return Err(anyhow!("no audio received; nothing to capture"));
+        }
+        thread::sleep(Duration::from_millis(16));
+    }
+
+    let buffer = terminal.backend().buffer();
+    export::save(buffer, buffer.area, path)
+}
+
+/// Keeps capturing and analyzing audio for the outputs alone, with nothing drawn.
+/// The control socket and MIDI input still work for everything that doesn't need a
+/// picture: tempo, sensitivity and queries. Runs until the process is interrupted.
+pub fn run_outputs(
+    input: &InputSource,
+    audio_config: &AudioConfig,
+    analyzer: Analyzer,
+    outputs: Outputs,
+    control: Option<&ControlServer>,
+    midi_in: Option<&MidiInput>,
+) -> Result<()> {
+    if outputs.is_empty() {
+        return Err(anyhow!("--no-tui needs at least one output enabled in the config"));
+    }
+
+    let mut audio = AudioInput::new(input, audio_config)?;
+    let worker = AnalysisWorker::spawn(audio.samples().clone(), analyzer, outputs)?;
+
+    loop {
+        audio.poll();
+
+        while let Some(command) = midi_in.and_then(|m| m.try_recv()) {
+            let analyzer = &mut *worker.analyzer();
+            match command {
+                MidiCommand::Sensitivity(value) => analyzer.detector.set_sensitivity(value),
+                MidiCommand::Action(Action::TapTempo) => analyzer.tempo.tap(),
+                MidiCommand::Action(Action::HalveTempo) => {
+                    analyzer.tempo.scale(0.5, analyzer.detected_bpm())
+                }
+                MidiCommand::Action(Action::DoubleTempo) => {
+                    analyzer.tempo.scale(2.0, analyzer.detected_bpm())
+                }
+                MidiCommand::Action(Action::ToggleTempoLock) => {
+                    analyzer.tempo.toggle_lock(analyzer.detected_bpm());
+                }
+                // Nothing is on show to switch or adjust.
+                MidiCommand::Action(_) | MidiCommand::Param(..) => {}
+            }
+        }
+
+        let Snapshot {
+            spectrum,
+            beat_info,
+        } = worker.take();
+
+        while let Some(request) = control.and_then(|c| c.try_recv()) {
+            let peak_freq = spectrum.as_ref().map(|s| get_peak_frequency(s).0);
+            let result = match &request.command {
+                Command::Sensitivity(value) if (1.0..=4.0).contains(value) => {
+                    worker.analyzer().detector.set_sensitivity(*value);
+                    Ok(json!(value))
+                }
+                Command::Sensitivity(_) => {
+                    Err("sensitivity must be between 1.0 and 4.0".to_string())
+                }
+                Command::Bpm => Ok(json!(beat_info.bpm)),
+                Command::Peak => Ok(json!(peak_freq)),
+                Command::Status => {
+                    let analyzer = worker.analyzer();
+                    Ok(json!({
+                        "bpm": beat_info.bpm,
+                        "tempo_locked": analyzer.tempo.is_locked(),
+                        "total_beats": beat_info.total_beats,
+                        "peak_freq": peak_freq,
+                        "sensitivity": analyzer.detector.sensitivity(),
+                        "audio": spectrum.is_some(),
+                    }))
+                }
+                _ => Err("not available with --no-tui".to_string()),
+            };
+            request.reply(result);
+        }
+
+        thread::sleep(Duration::from_millis(16));
+    }
+}
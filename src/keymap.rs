@@ -0,0 +1,370 @@
+use anyhow::{anyhow, bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    NextVisualizer,
+    PrevVisualizer,
+    /// Jump straight to the visualizer at this (zero-based) index.
+    Select(usize),
+    OpenPicker,
+    ToggleInfo,
+    ToggleHelp,
+    ToggleAutoCycle,
+    FocusNextPane,
+    FocusPrevPane,
+    CycleMarker,
+    CycleVisualizerMarker,
+    Pause,
+    CursorLeft,
+    CursorRight,
+    Screenshot,
+    TapTempo,
+    HalveTempo,
+    DoubleTempo,
+    ToggleTempoLock,
+}
+
+impl Action {
+    /// Every action, in the order they are listed in the help overlay.
+    pub fn all() -> Vec<Action> {
+        let mut actions = vec![
+            Action::Quit,
+            Action::NextVisualizer,
+            Action::PrevVisualizer,
+            Action::OpenPicker,
+            Action::ToggleInfo,
+            Action::ToggleHelp,
+            Action::ToggleAutoCycle,
+            Action::FocusNextPane,
+            Action::FocusPrevPane,
+            Action::CycleMarker,
+            Action::CycleVisualizerMarker,
+            Action::Pause,
+            Action::CursorLeft,
+            Action::CursorRight,
+            Action::Screenshot,
+            Action::TapTempo,
+            Action::HalveTempo,
+            Action::DoubleTempo,
+            Action::ToggleTempoLock,
+        ];
+        actions.extend((0..9).map(Action::Select));
+        actions
+    }
+
+    /// Name used for this action in the `[keys]` config section.
+    pub fn name(&self) -> String {
+        match self {
+            Action::Quit => "quit".into(),
+            Action::NextVisualizer => "next_visualizer".into(),
+            Action::PrevVisualizer => "prev_visualizer".into(),
+            Action::Select(idx) => format!("select_{}", idx + 1),
+            Action::OpenPicker => "open_picker".into(),
+            Action::ToggleInfo => "toggle_info".into(),
+            Action::ToggleHelp => "toggle_help".into(),
+            Action::ToggleAutoCycle => "toggle_auto_cycle".into(),
+            Action::FocusNextPane => "focus_next_pane".into(),
+            Action::FocusPrevPane => "focus_prev_pane".into(),
+            Action::CycleMarker => "cycle_marker".into(),
+            Action::CycleVisualizerMarker => "cycle_visualizer_marker".into(),
+            Action::Pause => "pause".into(),
+            Action::CursorLeft => "cursor_left".into(),
+            Action::CursorRight => "cursor_right".into(),
+            Action::Screenshot => "screenshot".into(),
+            Action::TapTempo => "tap_tempo".into(),
+            Action::HalveTempo => "halve_tempo".into(),
+            Action::DoubleTempo => "double_tempo".into(),
+            Action::ToggleTempoLock => "toggle_tempo_lock".into(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::all().into_iter().find(|a| a.name() == name)
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Action::Quit => "Quit".into(),
+            Action::NextVisualizer => "Next visualizer".into(),
+            Action::PrevVisualizer => "Previous visualizer".into(),
+            Action::Select(idx) => format!("Jump to visualizer {}", idx + 1),
+            Action::OpenPicker => "Open visualizer picker".into(),
+            Action::ToggleInfo => "Toggle info panel".into(),
+            Action::ToggleHelp => "Toggle this help".into(),
+            Action::ToggleAutoCycle => "Toggle auto-cycle playlist".into(),
+            Action::FocusNextPane => "Focus next pane".into(),
+            Action::FocusPrevPane => "Focus previous pane".into(),
+            Action::CycleMarker => "Cycle canvas marker".into(),
+            Action::CycleVisualizerMarker => "Cycle marker for this visualizer".into(),
+            Action::Pause => "Pause / freeze frame".into(),
+            Action::CursorLeft => "Move inspection cursor left (paused)".into(),
+            Action::CursorRight => "Move inspection cursor right (paused)".into(),
+            Action::Screenshot => "Save a screenshot of the visualization".into(),
+            Action::TapTempo => "Tap tempo".into(),
+            Action::HalveTempo => "Halve the tempo".into(),
+            Action::DoubleTempo => "Double the tempo".into(),
+            Action::ToggleTempoLock => "Lock beats to the tempo / back to detection".into(),
+        }
+    }
+
+    fn default_keys(&self) -> Vec<&'static str> {
+        match self {
+            Action::Quit => vec!["q", "ctrl+c"],
+            Action::NextVisualizer => vec!["tab", "right"],
+            Action::PrevVisualizer => vec!["backtab", "left"],
+            Action::Select(idx) => vec![["1", "2", "3", "4", "5", "6", "7", "8", "9"][*idx]],
+            Action::OpenPicker => vec!["v"],
+            Action::ToggleInfo => vec!["i"],
+            Action::ToggleHelp => vec!["?"],
+            Action::ToggleAutoCycle => vec!["a"],
+            Action::FocusNextPane => vec!["f"],
+            Action::FocusPrevPane => vec!["F"],
+            Action::CycleMarker => vec!["m"],
+            Action::CycleVisualizerMarker => vec!["M"],
+            Action::Pause => vec!["space", "p"],
+            Action::CursorLeft => vec![",", "shift+left"],
+            Action::CursorRight => vec![".", "shift+right"],
+            Action::Screenshot => vec!["s"],
+            Action::TapTempo => vec!["t"],
+            Action::HalveTempo => vec!["["],
+            Action::DoubleTempo => vec!["]"],
+            Action::ToggleTempoLock => vec!["l"],
+        }
+    }
+}
+
+/// A key plus the modifiers that must be held with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Parses strings like `q`, `?`, `ctrl+c`, `alt+left`, `shift+f5` or `space`.
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = s.split('+').collect();
+        // A bare "+" or a trailing "ctrl++" means the plus key itself.
+        let key = if s.ends_with("++") || s == "+" {
+            parts.retain(|p| !p.is_empty());
+            "+"
+        } else {
+            parts.pop().unwrap_or_default()
+        };
+
+        for part in parts {
+            modifiers |= match part.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => bail!("unknown modifier '{}' in key '{}'", other, s),
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            lower => {
+                if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    KeyCode::F(n)
+                } else {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c),
+                        _ => bail!("unknown key '{}'", s),
+                    }
+                }
+            }
+        };
+
+        // shift+tab is what terminals report as BackTab, and shift+a arrives as `A`.
+        let code = match code {
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+
+        Ok(Self::normalized(code, modifiers))
+    }
+
+    /// Characters already carry their case (`Q` vs `q`, `?`), and BackTab implies shift,
+    /// so SHIFT is dropped for them to match what different terminals report.
+    fn normalized(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self {
+            code,
+            modifiers: modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT),
+        }
+    }
+
+    /// Short human-readable label, as shown in the help overlay and info panel.
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("ctrl-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("alt-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            label.push_str("shift-");
+        }
+        match self.code {
+            KeyCode::Char(' ') => label.push_str("space"),
+            KeyCode::Char(c) => label.push(c),
+            KeyCode::BackTab => label.push_str("shift-tab"),
+            KeyCode::F(n) => label.push_str(&format!("f{}", n)),
+            KeyCode::PageUp => label.push_str("pgup"),
+            KeyCode::PageDown => label.push_str("pgdn"),
+            other => label.push_str(&other.to_string().to_lowercase()),
+        }
+        label
+    }
+}
+
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, Action)>,
+}
+
+impl Keymap {
+    /// Builds the keymap from the `[keys]` config section. Actions listed there replace
+    /// their default bindings; an empty list unbinds the action.
+    pub fn from_config(keys: &HashMap<String, Vec<String>>) -> Result<Self> {
+        let mut bindings = Vec::new();
+        for action in Action::all() {
+            match keys.get(&action.name()) {
+                Some(custom) => {
+                    for key in custom {
+                        bindings.push((KeyBinding::parse(key)?, action));
+                    }
+                }
+                None => {
+                    for key in action.default_keys() {
+                        bindings.push((KeyBinding::parse(key)?, action));
+                    }
+                }
+            }
+        }
+
+        if let Some(name) = keys.keys().find(|name| Action::from_name(name).is_none()) {
+            return Err(anyhow!("unknown action '{}' in [keys]", name));
+        }
+
+        for (i, (binding, action)) in bindings.iter().enumerate() {
+            if let Some((_, other)) = bindings[i + 1..]
+                .iter()
+                .find(|(b, a)| b == binding && a != action)
+            {
+                return Err(anyhow!(
+                    "key '{}' is bound to both '{}' and '{}' in [keys]",
+                    binding.label(),
+                    action.name(),
+                    other.name()
+                ));
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+
+    pub fn action_for(&self, key: &KeyEvent) -> Option<Action> {
+        let pressed = KeyBinding::normalized(key.code, key.modifiers);
+        self.bindings
+            .iter()
+            .find(|(binding, _)| *binding == pressed)
+            .map(|(_, action)| *action)
+    }
+
+    /// Labels of every key bound to `action`.
+    pub fn labels(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(binding, _)| binding.label())
+            .collect()
+    }
+
+    /// Label of the first key bound to `action`, or `-` if it is unbound.
+    pub fn hint(&self, action: Action) -> String {
+        self.labels(action)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        KeyBinding { code, modifiers }
+    }
+
+    #[test]
+    fn parses_keys_and_modifiers() {
+        let parse = |s| KeyBinding::parse(s).unwrap();
+        assert_eq!(parse("q"), key(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert_eq!(parse("ctrl+c"), key(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert_eq!(parse("Alt+Left"), key(KeyCode::Left, KeyModifiers::ALT));
+        assert_eq!(parse("space"), key(KeyCode::Char(' '), KeyModifiers::NONE));
+        assert_eq!(parse("+"), key(KeyCode::Char('+'), KeyModifiers::NONE));
+        assert_eq!(parse("ctrl++"), key(KeyCode::Char('+'), KeyModifiers::CONTROL));
+        // A lone `F` is the letter; `f5` is the function key.
+        assert_eq!(parse("F"), key(KeyCode::Char('F'), KeyModifiers::NONE));
+        assert_eq!(parse("f5"), key(KeyCode::F(5), KeyModifiers::NONE));
+        assert_eq!(parse("shift+f5"), key(KeyCode::F(5), KeyModifiers::SHIFT));
+
+        for bad in ["hyper+q", "ctrl+", "nokey", ""] {
+            assert!(KeyBinding::parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn shift_is_folded_into_characters() {
+        let parse = |s| KeyBinding::parse(s).unwrap();
+        assert_eq!(parse("shift+q"), parse("Q"));
+        assert_eq!(parse("shift+tab"), parse("backtab"));
+        // Terminals report shift+q as `Q` with SHIFT held.
+        let pressed = KeyEvent::new(KeyCode::Char('Q'), KeyModifiers::SHIFT);
+        assert_eq!(KeyBinding::normalized(pressed.code, pressed.modifiers), parse("Q"));
+    }
+
+    #[test]
+    fn rejects_a_key_bound_to_two_actions() {
+        let keys = HashMap::from([("pause".to_string(), vec!["q".to_string()])]);
+        let error = Keymap::from_config(&keys).err().unwrap().to_string();
+        assert!(error.contains("quit") && error.contains("pause"), "{}", error);
+
+        let keys = HashMap::from([
+            ("pause".to_string(), vec!["x".to_string()]),
+            ("quit".to_string(), vec!["q".to_string(), "ctrl+c".to_string()]),
+        ]);
+        let keymap = Keymap::from_config(&keys).unwrap();
+        let pressed = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
+        assert_eq!(keymap.action_for(&pressed), Some(Action::Pause));
+    }
+}
@@ -1,9 +1,8 @@
-use anyhow::Result;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use anyhow::{anyhow, Result};
+use clap::Parser;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    event::{self, Event, KeyEventKind},
+    terminal as term,
 };
 use ratatui::{
     backend::CrosstermBackend,
@@ -12,322 +11,396 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph},
     Terminal,
 };
-use spectrum_analyzer::{
-    scaling::divide_by_N, samples_fft_to_spectrum, windows::hann_window, FrequencyLimit,
-    FrequencySpectrum,
-};
+use serde_json::json;
+use spectrum_analyzer::FrequencySpectrum;
 use std::{
-    collections::VecDeque,
     io,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
-    },
+    process::ExitCode,
+    sync::{atomic::Ordering, Arc},
     time::{Duration, Instant},
 };
 
+mod analysis;
+mod audio;
+mod cli;
+mod clock;
+mod config;
+mod control;
+mod export;
+mod guard;
+mod headless;
+mod keymap;
+mod metrics;
+mod midi_in;
+mod output;
+mod playlist;
+mod record;
+mod render;
+mod ui;
 mod visualizers;
-use visualizers::{
-    bars::BarVisualizer,
-    liquid::LiquidWorld,
-    particles::{HorizontalParticles, MixedParticles, VerticalParticles},
-    radial::RadialVisualizer,
-    waveform::WaveformVisualizer,
-    waves::{LissajousEnhanced, LissajousInterference, ResonantHelix, SpectralRibbons},
-    BeatInfo, Visualizer,
+use analysis::{
+    get_peak_frequency,
+    worker::{AnalysisWorker, Snapshot},
+    Analyzer,
 };
-
-// --- Beat Detector ---
-
-struct BeatDetector {
-    energy_history: Vec<f32>,
-    history_size: usize,
-    sensitivity: f32,
-    last_beat: Instant,
-    intervals: VecDeque<Duration>,
-    total_beats: usize,
-}
-
-impl BeatDetector {
-    fn new(history_size: usize, sensitivity: f32) -> Self {
-        Self {
-            energy_history: Vec::with_capacity(history_size),
-            history_size,
-            sensitivity,
-            last_beat: Instant::now(),
-            intervals: VecDeque::with_capacity(10),
-            total_beats: 0,
+use audio::{AudioInput, AudioStatus};
+use cli::Cli;
+use config::Config;
+use control::{Command, ControlServer};
+use guard::TerminalGuard;
+use keymap::{Action, Keymap};
+use metrics::METRICS;
+use midi_in::{MidiCommand, MidiInput};
+use output::Outputs;
+use playlist::Playlist;
+use record::RecordingWriter;
+use ui::{
+    inspector::Inspector,
+    markers::MarkerSettings,
+    panes::Panes,
+    picker::{PickerOutcome, VisualizerPicker},
+    theme::Theme,
+    DrawContext,
+};
+use visualizers::BeatInfo;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // By now the terminal guard has been dropped, so this lands on the normal screen.
+            eprintln!("Error: {:#}", e);
+            guard::print_diagnostic();
+            ExitCode::FAILURE
         }
     }
+}
 
-    fn detect(&mut self, spectrum_data: &FrequencySpectrum) -> bool {
-        let mut low_energy = 0.0;
-        let mut count = 0;
-        
-        // Use a weighted average where lower frequencies (20-60Hz) are prioritized
-        // as they represent the "thump" of the kick drum more accurately.
-        for (freq, val) in spectrum_data.to_map().iter() {
-            let f = *freq as f32;
-            let v = *val;
-            if f >= 20.0 && f <= 150.0 {
-                let weight = if f <= 60.0 { 1.5 } else { 1.0 };
-                low_energy += v * weight;
-                count += 1;
-            }
-        }
-
-        if count == 0 {
-            return false;
-        }
-
-        let avg_low_energy = low_energy / count as f32;
-
-        if self.energy_history.is_empty() {
-            self.energy_history.push(avg_low_energy);
-            return false;
-        }
-
-        // Calculate both average and variance for a more dynamic threshold
-        let history_avg: f32 =
-            self.energy_history.iter().sum::<f32>() / self.energy_history.len() as f32;
-        
-        let variance: f32 = self.energy_history.iter()
-            .map(|e| (e - history_avg).powi(2))
-            .sum::<f32>() / self.energy_history.len() as f32;
-        
-        // A "beat" is a peak that stands out significantly from the local noise floor.
-        // We use a combination of sensitivity * average and a variance-based offset.
-        let dynamic_threshold = self.sensitivity * history_avg + variance.sqrt() * 0.5;
-
-        self.energy_history.push(avg_low_energy);
-        if self.energy_history.len() > self.history_size {
-            self.energy_history.remove(0);
-        }
+/// Length of a transition from `[transition] seconds`; negative means none.
+fn transition_duration(seconds: f32) -> Result<Duration> {
+    if !seconds.is_finite() {
+        return Err(anyhow!("[transition] seconds must be a finite number"));
+    }
+    Ok(Duration::try_from_secs_f32(seconds.max(0.0))?)
+}
 
-        let is_beat = avg_low_energy > dynamic_threshold && avg_low_energy > 0.01;
+fn run() -> Result<()> {
+    let cli = Cli::parse();
+    let app_config = Config::load(cli.config.as_deref())?;
+    let keymap = Keymap::from_config(&app_config.keys)?;
 
-        if is_beat {
-            let now = Instant::now();
-            let duration = now.duration_since(self.last_beat);
-            // Limit to ~200 BPM (300ms) to avoid double triggers
-            if duration.as_millis() > 300 {
-                self.intervals.push_back(duration);
-                if self.intervals.len() > 15 {
-                    self.intervals.pop_front();
-                }
-                self.last_beat = now;
-                self.total_beats += 1;
-            }
+    // Visualizers setup
+    let visualizer_names: Vec<String> = visualizers::names();
+    let visualizer_names: Vec<&str> = visualizer_names.iter().map(|n| n.as_str()).collect();
+    let mut playlist = Playlist::from_config(&app_config.playlist, &visualizer_names)?;
+    let find_visualizer = |name: &str, source: &str| {
+        visualizer_names
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow::anyhow!("unknown visualizer '{}' in {}", name, source))
+    };
+    let mut initial_panes = app_config
+        .layout
+        .panes
+        .iter()
+        .map(|name| find_visualizer(name, "[layout]"))
+        .collect::<Result<Vec<_>>>()?;
+    let requested = cli
+        .visualizer
+        .as_deref()
+        .map(|name| find_visualizer(name, "--visualizer"))
+        .transpose()?;
+    if let Some(first) = requested.or(playlist.first()) {
+        // The playlist drives the focused pane, which starts as the first one.
+        match initial_panes.first_mut() {
+            Some(pane) => *pane = first,
+            None => initial_panes.push(first),
         }
-
-        is_beat
     }
+    let mut markers = MarkerSettings::from_config(&app_config.markers, &visualizer_names)?;
+    let mut panes = Panes::new(
+        app_config.layout.preset,
+        &initial_panes,
+        app_config.transition.kind,
+        transition_duration(app_config.transition.seconds)?,
+    )?;
 
-    fn get_bpm(&self) -> f32 {
-        if self.intervals.len() < 3 {
-            return 0.0;
-        }
-        
-        // Use a median-like approach: sort intervals and pick the middle range 
-        // to ignore outliers (missed beats or accidental double triggers).
-        let mut sorted_intervals: Vec<u128> = self.intervals.iter()
-            .map(|d| d.as_millis())
-            .collect();
-        sorted_intervals.sort_unstable();
-        
-        let mid = sorted_intervals.len() / 2;
-        let median_ms = if sorted_intervals.len() % 2 == 0 {
-            (sorted_intervals[mid - 1] + sorted_intervals[mid]) as f32 / 2.0
-        } else {
-            sorted_intervals[mid] as f32
+    if let Some(input) = &cli.render {
+        let idx = initial_panes.first().copied().unwrap_or(0);
+        let options = render::RenderOptions {
+            input,
+            output: cli
+                .output
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("--render needs --output"))?,
+            fps: cli.fps,
+            size: cli.size,
+            visualizer: idx,
         };
-
-        if median_ms == 0.0 {
-            0.0
-        } else {
-            60000.0 / median_ms
-        }
+        return render::render(&options, markers.for_visualizer(idx));
     }
-}
 
-// --- Utils ---
-
-fn get_peak_frequency(spectrum: &FrequencySpectrum) -> (u32, f32) {
-    let mut max_val = 0.0;
-    let mut peak_freq = 0;
-    for (freq, val) in spectrum.to_map().iter() {
-        if *val > max_val {
-            max_val = *val;
-            peak_freq = *freq;
-        }
+    let input = cli.input_source();
+    if let Some(path) = &cli.screenshot {
+        return headless::screenshot(
+            path,
+            &input,
+            &app_config.audio,
+            cli.size,
+            cli.warmup,
+            &mut panes,
+            &markers,
+        );
     }
-    (peak_freq, max_val)
-}
-
-fn setup_audio_stream(
-    samples: Arc<Mutex<Vec<f32>>>,
-    restart_flag: Arc<AtomicBool>,
-) -> Result<(cpal::Stream, cpal::StreamConfig)> {
-    let host = cpal::default_host();
-    let device = host
-        .default_output_device()
-        .ok_or_else(|| anyhow::anyhow!("No output device found"))?;
-
-    let config: cpal::StreamConfig = device.default_output_config()?.into();
-    let channels = config.channels as usize;
-
-    let samples_clone = samples.clone();
-    let restart_flag_clone = restart_flag.clone();
-
-    let stream = device.build_input_stream(
-        &config,
-        move |data: &[f32], _: &_| {
-            let mut s = samples_clone.lock().unwrap();
-            if channels > 1 {
-                for frame in data.chunks_exact(channels) {
-                    let mono: f32 = frame.iter().sum::<f32>() / channels as f32;
-                    s.push(mono);
-                }
-            } else {
-                s.extend_from_slice(data);
-            }
-            
-            if s.len() > 4096 {
-                let keep = s.len() - 4096;
-                s.drain(0..keep);
-            }
-        },
-        move |_err| {
-            restart_flag_clone.store(true, Ordering::SeqCst);
-        },
-        None,
-    )?;
 
-    stream.play()?;
-    Ok((stream, config))
-}
+    let outputs = Outputs::from_config(&app_config)?;
+    metrics::serve(&app_config.metrics)?;
+    let control = ControlServer::from_config(&app_config.control)?;
+    let midi_in = MidiInput::from_config(&app_config.midi_in)?;
+    let mut analyzer = Analyzer::new();
+    analyzer.tempo.resync = app_config.tempo.resync;
+    if cli.no_tui {
+        return headless::run_outputs(
+            &input,
+            &app_config.audio,
+            analyzer,
+            outputs,
+            control.as_ref(),
+            midi_in.as_ref(),
+        );
+    }
 
-fn main() -> Result<()> {
     // 1. Setup Audio Capture
-    let samples = Arc::new(Mutex::new(Vec::new()));
-    let restart_flag = Arc::new(AtomicBool::new(false));
-
-    let (mut _stream, mut config) = match setup_audio_stream(samples.clone(), restart_flag.clone()) {
-        Ok(res) => (Some(res.0), res.1),
-        Err(_) => (
-            None,
-            cpal::StreamConfig {
-                channels: 1,
-                sample_rate: 44100,
-                buffer_size: cpal::BufferSize::Default,
-            },
-        ),
-    };
+    let mut audio = AudioInput::new(&input, &app_config.audio)?;
 
     // 2. Setup Terminal UI
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    let mut stdout = match &cli.record {
+        Some(path) => RecordingWriter::recording(io::stdout(), path, term::size()?)?,
+        None => RecordingWriter::new(io::stdout()),
+    };
+    let guard = TerminalGuard::enter(&mut stdout)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut beat_detector = BeatDetector::new(43, 1.5);
-    let mut is_beat = false;
-    let mut beat_timer = 0;
+    let worker = AnalysisWorker::spawn(audio.samples().clone(), analyzer, outputs)?;
 
-    // Visualizers setup
-    let visualizers: Vec<Box<dyn Visualizer>> = vec![
-        Box::new(WaveformVisualizer),
-        Box::new(BarVisualizer::new()),
-        Box::new(RadialVisualizer::new()),
-        Box::new(VerticalParticles::new()),
-        Box::new(HorizontalParticles::new()),
-        Box::new(MixedParticles::new()),
-        Box::new(LiquidWorld::new()),
-        Box::new(SpectralRibbons::new()),
-        Box::new(ResonantHelix::new()),
-        Box::new(LissajousInterference::new()),
-        Box::new(LissajousEnhanced::new()),
-    ];
-    let mut current_visualizer_index = 0;
     let mut show_info_panel = true;
+    let mut show_help = false;
+    let mut picker = VisualizerPicker::new();
+    let mut paused = false;
+    let mut frozen: Option<(Arc<FrequencySpectrum>, BeatInfo)> = None;
+    let mut inspector = Inspector::new();
+    let mut theme = Theme::default();
+    let mut screenshot_requested = false;
+    let mut status: Option<(String, Instant)> = None;
 
     let mut last_info_update = Instant::now();
     let mut displayed_peak_freq = 0;
 
     // 3. Main Render Loop
-    loop {
-        if _stream.is_none() || restart_flag.load(Ordering::SeqCst) {
-            if let Ok((new_stream, new_config)) =
-                setup_audio_stream(samples.clone(), restart_flag.clone())
-            {
-                _stream = Some(new_stream);
-                config = new_config;
-                restart_flag.store(false, Ordering::SeqCst);
-                // Clear old samples
-                let mut s = samples.lock().unwrap();
-                s.clear();
-            }
+    'main: loop {
+        if let Some(signal) = guard.signal() {
+            return Err(anyhow!("interrupted by {}", signal));
         }
 
+        audio.poll();
+
+        let mut actions = Vec::new();
         if event::poll(Duration::from_millis(16))? {
             if let Event::Key(key) = event::read()? {
                 if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char('q') => break,
-                        KeyCode::Char('i') => show_info_panel = !show_info_panel,
-                        KeyCode::Tab | KeyCode::Right => {
-                            current_visualizer_index =
-                                (current_visualizer_index + 1) % visualizers.len();
-                        }
-                        KeyCode::BackTab | KeyCode::Left => {
-                            current_visualizer_index =
-                                (current_visualizer_index + visualizers.len() - 1) % visualizers.len();
+                    if show_help {
+                        show_help = false;
+                        continue;
+                    }
+                    if picker.is_open() {
+                        if let PickerOutcome::Selected(idx) =
+                            picker.handle_key(key, &visualizer_names)
+                        {
+                            panes.focused_mut().select(idx);
+                            playlist.switched_to(panes.focused().current(), worker.analyzer().total_beats());
                         }
-                        _ => {}
+                        continue;
                     }
+                    actions.extend(keymap.action_for(&key));
                 }
             }
         }
 
-        let spectrum_data = {
-            let s = samples.lock().unwrap();
-            if s.len() >= 2048 {
-                let window = &s[s.len() - 2048..];
-                let hann_window = hann_window(window);
-
-                samples_fft_to_spectrum(
-                    &hann_window,
-                    config.sample_rate,
-                    FrequencyLimit::Range(20., 20_000.),
-                    Some(&divide_by_N),
-                )
-                .ok()
-            } else {
-                None
+        while let Some(command) = midi_in.as_ref().and_then(|m| m.try_recv()) {
+            match command {
+                MidiCommand::Action(action) => actions.push(action),
+                MidiCommand::Sensitivity(value) => worker.analyzer().detector.set_sensitivity(value),
+                MidiCommand::Param(name, value) => {
+                    panes.focused().set_param(&name, value);
+                }
             }
-        };
+        }
 
-        if let Some(ref spectrum) = spectrum_data {
-            if beat_detector.detect(spectrum) {
-                is_beat = true;
-                beat_timer = 5;
+        for action in actions {
+            let analyzer = &mut *worker.analyzer();
+            match action {
+                Action::Quit => break 'main,
+                Action::ToggleInfo => show_info_panel = !show_info_panel,
+                Action::ToggleHelp => show_help = true,
+                Action::OpenPicker => picker.open(panes.focused().current()),
+                Action::FocusNextPane => panes.focus_next(),
+                Action::FocusPrevPane => panes.focus_prev(),
+                Action::Pause => {
+                    paused = !paused;
+                    panes.set_frozen(paused);
+                    if !paused {
+                        frozen = None;
+                    }
+                }
+                Action::CursorLeft if paused => inspector.move_left(),
+                Action::CursorRight if paused => inspector.move_right(),
+                Action::Screenshot => screenshot_requested = true,
+                Action::TapTempo => analyzer.tempo.tap(),
+                Action::HalveTempo => analyzer.tempo.scale(0.5, analyzer.detected_bpm()),
+                Action::DoubleTempo => analyzer.tempo.scale(2.0, analyzer.detected_bpm()),
+                Action::ToggleTempoLock => {
+                    let locked = analyzer.tempo.toggle_lock(analyzer.detected_bpm());
+                    let message = if locked {
+                        "Tempo locked"
+                    } else if analyzer.detected_bpm() > 0.0 {
+                        "Following detected beats"
+                    } else {
+                        "Following detected beats (no tempo to lock to yet)"
+                    };
+                    status = Some((message.to_string(), Instant::now()));
+                }
+                Action::CycleMarker => markers.cycle_global(),
+                Action::CycleVisualizerMarker => {
+                    markers.cycle_override(panes.focused().current())
+                }
+                Action::ToggleAutoCycle => playlist.toggle(analyzer.total_beats()),
+                Action::Select(idx) if idx < visualizer_names.len() => {
+                    panes.focused_mut().select(idx);
+                    playlist.switched_to(panes.focused().current(), analyzer.total_beats());
+                }
+                Action::NextVisualizer => {
+                    panes.focused_mut().next();
+                    playlist.switched_to(panes.focused().current(), analyzer.total_beats());
+                }
+                Action::PrevVisualizer => {
+                    panes.focused_mut().prev();
+                    playlist.switched_to(panes.focused().current(), analyzer.total_beats());
+                }
+                _ => {}
             }
         }
 
-        if beat_timer > 0 {
-            beat_timer -= 1;
-        } else {
-            is_beat = false;
+        let Snapshot {
+            spectrum: mut spectrum_data,
+            beat_info,
+        } = worker.take();
+
+        while let Some(request) = control.as_ref().and_then(|c| c.try_recv()) {
+            let result = match &request.command {
+                Command::Visualizer(name) => {
+                    let idx = name
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|n| n.checked_sub(1))
+                        .filter(|&idx| idx < visualizer_names.len())
+                        .or_else(|| {
+                            visualizer_names
+                                .iter()
+                                .position(|n| n.eq_ignore_ascii_case(name))
+                        });
+                    match idx {
+                        Some(idx) => {
+                            panes.focused_mut().select(idx);
+                            playlist.switched_to(panes.focused().current(), beat_info.total_beats);
+                            Ok(json!(visualizer_names[idx]))
+                        }
+                        None => Err(format!("unknown visualizer '{}'", name)),
+                    }
+                }
+                Command::Next | Command::Prev => {
+                    if request.command == Command::Next {
+                        panes.focused_mut().next();
+                    } else {
+                        panes.focused_mut().prev();
+                    }
+                    playlist.switched_to(panes.focused().current(), beat_info.total_beats);
+                    Ok(json!(visualizer_names[panes.focused().current()]))
+                }
+                Command::Info(on) => {
+                    show_info_panel = on.unwrap_or(!show_info_panel);
+                    Ok(json!(show_info_panel))
+                }
+                Command::Pause(on) => {
+                    let want = on.unwrap_or(!paused);
+                    if want != paused {
+                        paused = want;
+                        panes.set_frozen(paused);
+                        if !paused {
+                            frozen = None;
+                        }
+                    }
+                    Ok(json!(paused))
+                }
+                Command::Sensitivity(value) if (1.0..=4.0).contains(value) => {
+                    worker.analyzer().detector.set_sensitivity(*value);
+                    Ok(json!(value))
+                }
+                Command::Sensitivity(_) => Err("sensitivity must be between 1.0 and 4.0".to_string()),
+                Command::Theme(name) => match Theme::from_name(name) {
+                    Some(selected) => {
+                        theme = selected;
+                        Ok(json!(theme.name()))
+                    }
+                    None => Err(format!(
+                        "unknown theme '{}'; expected one of {}",
+                        name,
+                        Theme::ALL.map(Theme::name).join(", ")
+                    )),
+                },
+                Command::Bpm => Ok(json!(beat_info.bpm)),
+                Command::Peak => Ok(json!(spectrum_data.as_ref().map(|s| get_peak_frequency(s).0))),
+                Command::Status => {
+                    let analyzer = worker.analyzer();
+                    Ok(json!({
+                        "visualizer": visualizer_names[panes.focused().current()],
+                        "paused": paused,
+                        "info": show_info_panel,
+                        "bpm": beat_info.bpm,
+                        "tempo_locked": analyzer.tempo.is_locked(),
+                        "total_beats": beat_info.total_beats,
+                        "peak_freq": spectrum_data.as_ref().map(|s| get_peak_frequency(s).0),
+                        "sensitivity": analyzer.detector.sensitivity(),
+                        "theme": theme.name(),
+                        "audio": spectrum_data.is_some(),
+                    }))
+                }
+            };
+            request.reply(result);
+        }
+
+        if !paused && let Some(idx) = playlist.tick(beat_info.total_beats) {
+            panes.focused_mut().select(idx);
         }
 
-        let beat_info = BeatInfo {
-            is_beat,
-            bpm: beat_detector.get_bpm(),
-            total_beats: beat_detector.total_beats,
+        // Capture keeps running while paused, but the picture is drawn from the frozen frame.
+        if paused && frozen.is_none() {
+            frozen = spectrum_data.take().map(|s| (s, beat_info.clone()));
+        }
+        let (spectrum_data, beat_info) = match &frozen {
+            Some((spectrum, frozen_info)) => (Some(spectrum), frozen_info),
+            None => (spectrum_data.as_ref(), &beat_info),
         };
 
-        terminal.draw(|f| {
+        let mut draw_result = Ok(());
+        let mut visualization_area = Default::default();
+        if status.as_ref().is_some_and(|(_, at)| at.elapsed() > Duration::from_secs(4)) {
+            status = None;
+        }
+
+        let tempo_locked = worker.analyzer().tempo.is_locked();
+        let draw_started = Instant::now();
+        let frame = terminal.draw(|f| {
             let layout = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints(if show_info_panel {
@@ -336,10 +409,21 @@ fn main() -> Result<()> {
                     &[Constraint::Min(0)][..]
                 })
                 .split(f.area());
+            visualization_area = layout[0];
 
-            if let Some(spectrum) = &spectrum_data {
+            if let Some(spectrum) = spectrum_data {
                 // Main Visualization
-                visualizers[current_visualizer_index].draw(f, layout[0], spectrum, &beat_info);
+                let ctx = DrawContext {
+                    spectrum,
+                    beat_info,
+                    markers: &markers,
+                };
+                draw_result = panes.draw(f, layout[0], &ctx);
+                theme.apply(f.buffer_mut(), layout[0]);
+
+                if paused && let Some(axis) = panes.focused().frequency_axis() {
+                    inspector.draw(f, panes.focused_area(layout[0]), axis, spectrum);
+                }
 
                 if show_info_panel {
                     // Update peak frequency only every 200ms to keep it readable
@@ -350,17 +434,47 @@ fn main() -> Result<()> {
                     }
 
                     let info_text = format!(
-                        " Peak Freq: {:>5} Hz | Est. BPM: {:>5.1} | Beats: {:>4} | Controls: [q]uit, [right/tab] next, [left/shift-tab] prev, [i]nfo",
-                        displayed_peak_freq, beat_info.bpm, beat_info.total_beats
+                        " Peak Freq: {:>5} Hz | {} BPM: {:>5.1} | Beats: {:>4} | Marker: {} | Controls: [{}] help, [{}] visualizers, [{}] next, [{}] quit",
+                        displayed_peak_freq,
+                        if tempo_locked { "Lock" } else { "Est." },
+                        beat_info.bpm,
+                        beat_info.total_beats,
+                        markers.label(panes.focused().current()),
+                        keymap.hint(Action::ToggleHelp),
+                        keymap.hint(Action::OpenPicker),
+                        keymap.hint(Action::NextVisualizer),
+                        keymap.hint(Action::Quit),
                     );
 
+                    let title = if let Some((message, _)) = &status {
+                        format!(" Audio Intelligence | {} ", message)
+                    } else if paused {
+                        format!(
+                            " Audio Intelligence | PAUSED: [{}] resume, [{}]/[{}] move cursor ",
+                            keymap.hint(Action::Pause),
+                            keymap.hint(Action::CursorLeft),
+                            keymap.hint(Action::CursorRight),
+                        )
+                    } else if playlist.is_enabled() {
+                        format!(
+                            " Audio Intelligence | Auto-cycle: next in {} ",
+                            playlist.remaining(beat_info.total_beats)
+                        )
+                    } else {
+                        " Audio Intelligence ".to_string()
+                    };
+
                     let info_panel = Paragraph::new(info_text)
                         .block(
                             Block::default()
                                 .borders(Borders::ALL)
-                                .title(" Audio Intelligence "),
+                                .title(title)
+                                .title_bottom(
+                                    ratatui::text::Line::from(format!(" {} ", audio.status()))
+                                        .right_aligned(),
+                                ),
                         )
-                        .style(Style::default().fg(if is_beat {
+                        .style(Style::default().fg(if beat_info.is_beat {
                             Color::Magenta
                         } else {
                             Color::White
@@ -379,12 +493,19 @@ fn main() -> Result<()> {
                     _ => " ♪  ♫  ♬  ♩  ♭ ",
                 };
                 
+                let status = audio.status();
+                let heading = match status {
+                    AudioStatus::Failed { .. } => "No Audio Input",
+                    _ => "Waiting for Audio",
+                };
                 let waiting_msg = Paragraph::new(vec![
                     ratatui::text::Line::from(""),
                     ratatui::text::Line::from(""),
-                    ratatui::text::Line::from("Waiting for Audio").style(Style::default().fg(Color::Cyan)),
+                    ratatui::text::Line::from(heading).style(Style::default().fg(Color::Cyan)),
                     ratatui::text::Line::from(""),
                     ratatui::text::Line::from(notes).style(Style::default().fg(Color::Cyan)),
+                    ratatui::text::Line::from(""),
+                    ratatui::text::Line::from(status.to_string()).style(Style::default().fg(Color::DarkGray)),
                 ])
                 .alignment(ratatui::layout::Alignment::Center)
                 .block(Block::default().borders(Borders::ALL).title(" Initializing "));
@@ -392,18 +513,46 @@ fn main() -> Result<()> {
                 f.render_widget(waiting_msg, layout[0]);
 
                 if show_info_panel {
-                    let info_text = " Status: Ready | Listening for sound source... | Controls: [q]uit, [i]nfo";
+                    let info_text = format!(
+                        " Status: Ready | Listening for sound source... | Controls: [{}] help, [{}] quit",
+                        keymap.hint(Action::ToggleHelp),
+                        keymap.hint(Action::Quit),
+                    );
                     let info_panel = Paragraph::new(info_text)
                         .block(Block::default().borders(Borders::ALL).title(" Audio Intelligence "))
                         .style(Style::default().fg(Color::DarkGray));
                     f.render_widget(info_panel, layout[1]);
                 }
             }
+
+            if picker.is_open() {
+                picker.draw(f, f.area(), &visualizer_names, panes.focused().current());
+            }
+            if show_help {
+                ui::help::draw_help(f, f.area(), &keymap);
+            }
         })?;
+        METRICS.render_time.observe(draw_started.elapsed());
+        METRICS.frames_rendered.fetch_add(1, Ordering::Relaxed);
+
+        if screenshot_requested {
+            screenshot_requested = false;
+            let message = match export::save_all(
+                frame.buffer,
+                visualization_area,
+                &app_config.export.dir,
+                &app_config.export.formats,
+            ) {
+                Ok(paths) => match paths.first() {
+                    Some(path) => format!("Saved {}", path.with_extension("*").display()),
+                    None => "No screenshot formats configured".to_string(),
+                },
+                Err(e) => format!("Screenshot failed: {:#}", e),
+            };
+            status = Some((message, Instant::now()));
+        }
+        draw_result?;
     }
 
-    // Cleanup
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     Ok(())
 }
@@ -0,0 +1,281 @@
+use crate::config::MetricsConfig;
+use anyhow::{Context, Result};
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// Upper bounds, in seconds, of the time histogram buckets.
+const BUCKETS: [f64; 10] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.016, 0.025, 0.05, 0.1, 0.25,
+];
+
+/// Cumulative-free histogram of durations; buckets are summed up when exported.
+pub struct Histogram {
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; BUCKETS.len()],
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|&bound| secs <= bound) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn write(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        let mut cumulative = 0;
+        for (bound, bucket) in BUCKETS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(out, "{}_sum {}", name, sum);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+/// An `f32` that can be shared between threads.
+pub struct Gauge(AtomicU32);
+
+impl Gauge {
+    const fn new() -> Self {
+        Self(AtomicU32::new(0))
+    }
+
+    pub fn set(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Process-wide counters and gauges, updated from the render loop and the audio
+/// callback and read by the metrics endpoint.
+pub struct Metrics {
+    pub frames_rendered: AtomicU64,
+    pub render_time: Histogram,
+    pub fft_time: Histogram,
+    /// Buffer underruns/overruns reported by the audio backend.
+    pub audio_overruns: AtomicU64,
+    /// Capture restarts after the stream reported an error.
+    pub stream_restarts: AtomicU64,
+    pub bpm: Gauge,
+    pub total_beats: AtomicU64,
+    /// RMS of the most recent analysis window.
+    pub input_level: Gauge,
+}
+
+pub static METRICS: Metrics = Metrics {
+    frames_rendered: AtomicU64::new(0),
+    render_time: Histogram::new(),
+    fft_time: Histogram::new(),
+    audio_overruns: AtomicU64::new(0),
+    stream_restarts: AtomicU64::new(0),
+    bpm: Gauge::new(),
+    total_beats: AtomicU64::new(0),
+    input_level: Gauge::new(),
+};
+
+impl Metrics {
+    /// Prometheus text exposition format.
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let mut counter = |name: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        };
+        counter(
+            "visualizer_frames_rendered_total",
+            "Frames drawn to the terminal.",
+            self.frames_rendered.load(Ordering::Relaxed),
+        );
+        counter(
+            "visualizer_audio_overruns_total",
+            "Buffer underruns or overruns reported by the audio backend.",
+            self.audio_overruns.load(Ordering::Relaxed),
+        );
+        counter(
+            "visualizer_stream_restarts_total",
+            "Audio stream restarts after a stream error.",
+            self.stream_restarts.load(Ordering::Relaxed),
+        );
+        counter(
+            "visualizer_beats_total",
+            "Beats detected.",
+            self.total_beats.load(Ordering::Relaxed),
+        );
+
+        let mut gauge = |name: &str, help: &str, value: f32| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        };
+        gauge("visualizer_bpm", "Current estimated tempo.", self.bpm.get());
+        gauge(
+            "visualizer_input_level",
+            "RMS level of the latest analysis window (0-1).",
+            self.input_level.get(),
+        );
+
+        self.render_time.write(
+            &mut out,
+            "visualizer_render_seconds",
+            "Time spent drawing one frame.",
+        );
+        self.fft_time.write(
+            &mut out,
+            "visualizer_fft_seconds",
+            "Time spent computing one spectrum.",
+        );
+        out
+    }
+}
+
+/// Serves [`METRICS`] over HTTP on a background thread, if enabled.
+pub fn serve(config: &MetricsConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    let listener = TcpListener::bind(&config.bind)
+        .with_context(|| format!("failed to listen for metrics on {}", config.bind))?;
+    thread::Builder::new()
+        .name("metrics-http".into())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // Each on its own thread, so a client that connects and says nothing
+                // can't hold up the scrapes behind it.
+                let _ = thread::Builder::new()
+                    .name("metrics-client".into())
+                    .spawn(move || respond(stream));
+            }
+        })?;
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    let mut reader = BufReader::new(stream.try_clone()?);
+    reader.read_line(&mut request_line)?;
+    // Drain the headers so the client sees a clean response.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (status, body) = if path == "/metrics" || path.starts_with("/metrics?") {
+        ("200 OK", METRICS.render())
+    } else {
+        ("404 Not Found", "see /metrics\n".to_string())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposition_format() {
+        let metrics = Metrics {
+            frames_rendered: AtomicU64::new(3),
+            render_time: Histogram::new(),
+            fft_time: Histogram::new(),
+            audio_overruns: AtomicU64::new(1),
+            stream_restarts: AtomicU64::new(0),
+            bpm: Gauge::new(),
+            total_beats: AtomicU64::new(12),
+            input_level: Gauge::new(),
+        };
+        metrics.bpm.set(120.5);
+        metrics.input_level.set(0.25);
+        metrics.render_time.observe(Duration::from_micros(800));
+        metrics.render_time.observe(Duration::from_millis(20));
+        // Beyond the last bucket: only counted in +Inf.
+        metrics.render_time.observe(Duration::from_secs(1));
+
+        let text = metrics.render();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            &lines[..12],
+            [
+                "# HELP visualizer_frames_rendered_total Frames drawn to the terminal.",
+                "# TYPE visualizer_frames_rendered_total counter",
+                "visualizer_frames_rendered_total 3",
+                "# HELP visualizer_audio_overruns_total Buffer underruns or overruns reported by \
+                 the audio backend.",
+                "# TYPE visualizer_audio_overruns_total counter",
+                "visualizer_audio_overruns_total 1",
+                "# HELP visualizer_stream_restarts_total Audio stream restarts after a stream error.",
+                "# TYPE visualizer_stream_restarts_total counter",
+                "visualizer_stream_restarts_total 0",
+                "# HELP visualizer_beats_total Beats detected.",
+                "# TYPE visualizer_beats_total counter",
+                "visualizer_beats_total 12",
+            ]
+        );
+        assert!(text.contains("# TYPE visualizer_bpm gauge\nvisualizer_bpm 120.5\n"));
+        assert!(text.contains("visualizer_input_level 0.25\n"));
+
+        let histogram = text
+            .split("# HELP visualizer_render_seconds")
+            .nth(1)
+            .unwrap()
+            .split("# HELP")
+            .next()
+            .unwrap();
+        assert_eq!(
+            histogram,
+            " Time spent drawing one frame.
+# TYPE visualizer_render_seconds histogram
+visualizer_render_seconds_bucket{le=\"0.0005\"} 0
+visualizer_render_seconds_bucket{le=\"0.001\"} 1
+visualizer_render_seconds_bucket{le=\"0.0025\"} 1
+visualizer_render_seconds_bucket{le=\"0.005\"} 1
+visualizer_render_seconds_bucket{le=\"0.01\"} 1
+visualizer_render_seconds_bucket{le=\"0.016\"} 1
+visualizer_render_seconds_bucket{le=\"0.025\"} 2
+visualizer_render_seconds_bucket{le=\"0.05\"} 2
+visualizer_render_seconds_bucket{le=\"0.1\"} 2
+visualizer_render_seconds_bucket{le=\"0.25\"} 2
+visualizer_render_seconds_bucket{le=\"+Inf\"} 3
+visualizer_render_seconds_sum 1.0208
+visualizer_render_seconds_count 3
+"
+        );
+        assert!(text.ends_with("visualizer_fft_seconds_count 0\n"));
+    }
+}
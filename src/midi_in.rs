@@ -0,0 +1,369 @@
+use crate::{
+    config::{MidiInConfig, MidiMapping},
+    keymap::Action,
+    visualizers,
+};
+use anyhow::{anyhow, Result};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// What a mapped control asks the main loop to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MidiCommand {
+    /// Same as pressing the key bound to this action.
+    Action(Action),
+    /// Beat detection threshold multiplier, 1.0-4.0.
+    Sensitivity(f32),
+    /// A live parameter of the visualizer on show, 0.0-1.0 across its range.
+    Param(String, f32),
+}
+
+/// Target of one `[[midi_in.map]]` entry, resolved from its `action` name.
+#[derive(Debug, Clone)]
+enum Target {
+    Action(Action),
+    Sensitivity,
+    Param(String),
+}
+
+/// An incoming channel message, with the channel zero-based.
+#[derive(Debug, Clone, Copy)]
+enum Message {
+    NoteOn { channel: u8, note: u8 },
+    Controller { channel: u8, cc: u8, value: u8 },
+}
+
+/// Config mappings with their targets resolved, plus the CC state needed to treat
+/// controllers as buttons.
+struct Mapper {
+    mappings: Vec<(MidiMapping, Target)>,
+    /// Last value seen per (channel, controller).
+    last_cc: [[u8; 128]; 16],
+}
+
+impl Mapper {
+    fn new(config: &MidiInConfig) -> Result<Self> {
+        let mappings = config
+            .map
+            .iter()
+            .map(|mapping| {
+                if mapping.note.is_some() == mapping.cc.is_some() {
+                    return Err(anyhow!(
+                        "MIDI mapping for '{}' needs exactly one of `note` or `cc`",
+                        mapping.action
+                    ));
+                }
+                if mapping.channel.is_some_and(|c| !(1..=16).contains(&c)) {
+                    return Err(anyhow!("MIDI channel for '{}' is outside 1-16", mapping.action));
+                }
+                let target = if mapping.action == "sensitivity" {
+                    // A value to follow, which a note doesn't carry.
+                    if mapping.cc.is_none() {
+                        return Err(anyhow!("MIDI mapping for '{}' needs a `cc`", mapping.action));
+                    }
+                    Target::Sensitivity
+                } else if let Some(param) = mapping.action.strip_prefix("param:") {
+                    if !visualizers::has_param(param) {
+                        return Err(anyhow!("no visualizer has a parameter '{}' in [midi_in]", param));
+                    }
+                    if mapping.cc.is_none() {
+                        return Err(anyhow!("MIDI mapping for '{}' needs a `cc`", mapping.action));
+                    }
+                    Target::Param(param.to_string())
+                } else {
+                    Target::Action(Action::from_name(&mapping.action).ok_or_else(|| {
+                        anyhow!("unknown action '{}' in [midi_in]", mapping.action)
+                    })?)
+                };
+                Ok((mapping.clone(), target))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            mappings,
+            last_cc: [[0; 128]; 16],
+        })
+    }
+
+    /// Commands triggered by `message`. Notes trigger on note-on; controllers mapped
+    /// to an action trigger when they cross the halfway point upwards, so both
+    /// momentary buttons (0/127) and knobs work.
+    fn map(&mut self, message: Message) -> Vec<MidiCommand> {
+        let (channel, previous) = match message {
+            Message::Controller { channel, cc, value } => {
+                let slot = &mut self.last_cc[channel as usize & 15][cc as usize & 127];
+                (channel, std::mem::replace(slot, value))
+            }
+            Message::NoteOn { channel, .. } => (channel, 0),
+        };
+
+        self.mappings
+            .iter()
+            .filter(|(mapping, _)| mapping.channel.is_none_or(|c| c == channel + 1))
+            .filter_map(|(mapping, target)| match (message, target) {
+                (Message::NoteOn { note, .. }, Target::Action(action)) if mapping.note == Some(note) => {
+                    Some(MidiCommand::Action(*action))
+                }
+                (Message::Controller { cc, value, .. }, Target::Action(action))
+                    if mapping.cc == Some(cc) && previous < 64 && value >= 64 =>
+                {
+                    Some(MidiCommand::Action(*action))
+                }
+                (Message::Controller { cc, value, .. }, Target::Sensitivity) if mapping.cc == Some(cc) => {
+                    Some(MidiCommand::Sensitivity(1.0 + 3.0 * value as f32 / 127.0))
+                }
+                (Message::Controller { cc, value, .. }, Target::Param(param)) if mapping.cc == Some(cc) => {
+                    Some(MidiCommand::Param(param.clone(), value as f32 / 127.0))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// MIDI controller input through an ALSA sequencer port.
+///
+/// The port is writable by anyone, so a controller can be patched in with
+/// `aconnect`, or connected on startup by naming it in `connect`. Incoming notes
+/// and controllers are matched against `[[midi_in.map]]` on a background thread;
+/// the main loop picks up the resulting commands with [`try_recv`](Self::try_recv).
+pub struct MidiInput {
+    commands: Receiver<MidiCommand>,
+}
+
+impl MidiInput {
+    /// Returns `None` when MIDI input is switched off.
+    pub fn from_config(config: &MidiInConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let mapper = Mapper::new(config)?;
+        let (tx, commands) = mpsc::channel();
+        sequencer::spawn(&config.port_name, config.connect.as_deref(), mapper, tx)?;
+        Ok(Some(Self { commands }))
+    }
+
+    /// The next command waiting to be applied, if any.
+    pub fn try_recv(&self) -> Option<MidiCommand> {
+        self.commands.try_recv().ok()
+    }
+}
+
+/// Maps `message` and forwards the results; false once the main loop has gone.
+fn dispatch(mapper: &mut Mapper, message: Message, commands: &Sender<MidiCommand>) -> bool {
+    mapper
+        .map(message)
+        .into_iter()
+        .all(|command| commands.send(command).is_ok())
+}
+
+#[cfg(target_os = "linux")]
+mod sequencer {
+    use super::{dispatch, Mapper, Message, MidiCommand};
+    use alsa::seq::{
+        Addr, ClientIter, EvCtrl, EvNote, EventType, PortCap, PortIter, PortSubscribe, PortType, Seq,
+    };
+    use anyhow::{anyhow, Context, Result};
+    use std::{ffi::CString, sync::mpsc::Sender, thread};
+
+    pub(super) fn spawn(
+        port_name: &str,
+        connect: Option<&str>,
+        mut mapper: Mapper,
+        commands: Sender<MidiCommand>,
+    ) -> Result<()> {
+        let seq = Seq::open(None, Some(alsa::Direction::Capture), false)
+            .context("failed to open the ALSA sequencer")?;
+        let name = CString::new(port_name).context("invalid MIDI port name")?;
+        seq.set_client_name(&name)?;
+        let port = seq
+            .create_simple_port(
+                &name,
+                PortCap::WRITE | PortCap::SUBS_WRITE,
+                PortType::MIDI_GENERIC | PortType::APPLICATION,
+            )
+            .context("failed to create MIDI port")?;
+
+        if let Some(source) = connect {
+            let sender = find_source(&seq, source)?;
+            let subscription = PortSubscribe::empty()?;
+            subscription.set_sender(sender);
+            subscription.set_dest(Addr {
+                client: seq.client_id()?,
+                port,
+            });
+            seq.subscribe_port(&subscription)
+                .with_context(|| format!("failed to connect MIDI input from '{}'", source))?;
+        }
+
+        thread::Builder::new()
+            .name("midi-input".into())
+            .spawn(move || {
+                let mut input = seq.input();
+                while let Ok(event) = input.event_input() {
+                    let message = match event.get_type() {
+                        EventType::Noteon => match event.get_data::<EvNote>() {
+                            // Velocity 0 is a note-off by MIDI convention.
+                            Some(note) if note.velocity > 0 => Message::NoteOn {
+                                channel: note.channel,
+                                note: note.note,
+                            },
+                            _ => continue,
+                        },
+                        EventType::Controller => match event.get_data::<EvCtrl>() {
+                            Some(ctrl) if ctrl.param < 128 => Message::Controller {
+                                channel: ctrl.channel,
+                                cc: ctrl.param as u8,
+                                value: ctrl.value.clamp(0, 127) as u8,
+                            },
+                            _ => continue,
+                        },
+                        _ => continue,
+                    };
+                    if !dispatch(&mut mapper, message, &commands) {
+                        return;
+                    }
+                }
+            })?;
+        Ok(())
+    }
+
+    /// First subscribable output port of the client whose name contains `source`.
+    fn find_source(seq: &Seq, source: &str) -> Result<Addr> {
+        for client in ClientIter::new(seq) {
+            if !client.get_name().is_ok_and(|name| name.contains(source)) {
+                continue;
+            }
+            let port = PortIter::new(seq, client.get_client()).find(|port| {
+                port.get_capability()
+                    .contains(PortCap::READ | PortCap::SUBS_READ)
+            });
+            if let Some(port) = port {
+                return Ok(port.addr());
+            }
+        }
+        Err(anyhow!("no MIDI device named '{}' found", source))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sequencer {
+    use super::{Mapper, MidiCommand};
+    use anyhow::{anyhow, Result};
+    use std::sync::mpsc::Sender;
+
+    pub(super) fn spawn(
+        _port_name: &str,
+        _connect: Option<&str>,
+        _mapper: Mapper,
+        _commands: Sender<MidiCommand>,
+    ) -> Result<()> {
+        Err(anyhow!("MIDI input needs the ALSA sequencer, which is only available on Linux"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapper(map: Vec<MidiMapping>) -> Mapper {
+        Mapper::new(&MidiInConfig {
+            enabled: true,
+            map,
+            ..MidiInConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn cc(cc: u8, channel: Option<u8>, action: &str) -> MidiMapping {
+        MidiMapping {
+            note: None,
+            cc: Some(cc),
+            channel,
+            action: action.to_string(),
+        }
+    }
+
+    fn controller(channel: u8, cc: u8, value: u8) -> Message {
+        Message::Controller { channel, cc, value }
+    }
+
+    #[test]
+    fn controller_triggers_action_when_crossing_halfway() {
+        let mut mapper = mapper(vec![cc(20, None, "next_visualizer")]);
+        let next = vec![MidiCommand::Action(Action::NextVisualizer)];
+
+        assert!(mapper.map(controller(0, 20, 63)).is_empty());
+        assert_eq!(mapper.map(controller(0, 20, 64)), next);
+        // Staying above halfway doesn't repeat the action.
+        assert!(mapper.map(controller(0, 20, 127)).is_empty());
+        assert!(mapper.map(controller(0, 20, 0)).is_empty());
+        assert_eq!(mapper.map(controller(0, 20, 127)), next);
+        // Other controllers are tracked separately.
+        assert!(mapper.map(controller(0, 21, 127)).is_empty());
+    }
+
+    #[test]
+    fn mappings_only_match_their_channel() {
+        let mut mapper = mapper(vec![
+            cc(20, Some(2), "pause"),
+            MidiMapping {
+                note: Some(60),
+                cc: None,
+                channel: None,
+                action: "quit".to_string(),
+            },
+        ]);
+
+        assert!(mapper.map(controller(0, 20, 127)).is_empty());
+        assert_eq!(
+            mapper.map(controller(1, 20, 127)),
+            vec![MidiCommand::Action(Action::Pause)]
+        );
+        for channel in [0, 9, 15] {
+            assert_eq!(
+                mapper.map(Message::NoteOn { channel, note: 60 }),
+                vec![MidiCommand::Action(Action::Quit)]
+            );
+        }
+        assert!(mapper.map(Message::NoteOn { channel: 0, note: 61 }).is_empty());
+    }
+
+    #[test]
+    fn sensitivity_and_params_follow_controller_value() {
+        let mut mapper = mapper(vec![cc(1, None, "sensitivity"), cc(2, None, "param:spin")]);
+
+        assert_eq!(mapper.map(controller(0, 1, 0)), vec![MidiCommand::Sensitivity(1.0)]);
+        assert_eq!(mapper.map(controller(0, 1, 127)), vec![MidiCommand::Sensitivity(4.0)]);
+        // Continuous targets follow every change, not just the halfway crossing.
+        assert_eq!(mapper.map(controller(0, 1, 126)).len(), 1);
+        assert_eq!(
+            mapper.map(controller(0, 2, 127)),
+            vec![MidiCommand::Param("spin".to_string(), 1.0)]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_mappings() {
+        let invalid = |mapping| {
+            Mapper::new(&MidiInConfig {
+                map: vec![mapping],
+                ..MidiInConfig::default()
+            })
+            .is_err()
+        };
+        assert!(invalid(cc(1, None, "no_such_action")));
+        assert!(invalid(cc(1, None, "param:no_such_param")));
+        assert!(invalid(cc(1, Some(17), "pause")));
+        assert!(invalid(MidiMapping {
+            note: Some(60),
+            cc: None,
+            channel: None,
+            action: "param:spin".to_string(),
+        }));
+        assert!(invalid(MidiMapping {
+            note: Some(60),
+            cc: None,
+            channel: None,
+            action: "sensitivity".to_string(),
+        }));
+    }
+}
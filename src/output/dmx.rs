@@ -0,0 +1,382 @@
+use super::{hue_to_rgb, AutoGain};
+use crate::{
+    analysis::{get_peak_frequency, Bands},
+    config::{DmxChannelConfig, DmxConfig},
+    visualizers::BeatInfo,
+};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use spectrum_analyzer::FrequencySpectrum;
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DmxProtocol {
+    /// Art-Net 4 ArtDmx packets, broadcast on port 6454 by default.
+    ArtNet,
+    /// E1.31 (streaming ACN), multicast to the universe's group by default.
+    Sacn,
+}
+
+/// Analysis value a DMX channel can follow. All are scaled to 0..1 before mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DmxFeature {
+    /// Jumps to full on each beat, then fades out over `beat_decay` seconds.
+    Beat,
+    Sub,
+    Bass,
+    Mid,
+    High,
+    /// Hue of the dominant note's pitch class (C = 0, going round the color wheel).
+    NoteHue,
+    /// Red, green and blue of that hue at full saturation, for RGB fixtures.
+    NoteRed,
+    NoteGreen,
+    NoteBlue,
+}
+
+/// Encodes an ArtDmx packet.
+fn artnet_packet(universe: u16, sequence: u8, data: &[u8; 512]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(18 + 512);
+    packet.extend_from_slice(b"Art-Net\0");
+    packet.extend_from_slice(&0x5000u16.to_le_bytes()); // OpDmx
+    packet.extend_from_slice(&14u16.to_be_bytes()); // protocol version
+    packet.push(sequence);
+    packet.push(0); // physical port
+    packet.push((universe & 0xFF) as u8); // SubUni
+    packet.push(((universe >> 8) & 0x7F) as u8); // Net
+    packet.extend_from_slice(&512u16.to_be_bytes());
+    packet.extend_from_slice(data);
+    packet
+}
+
+/// Encodes an E1.31 data packet (root, framing and DMP layers).
+fn sacn_packet(universe: u16, sequence: u8, cid: &[u8; 16], data: &[u8; 512]) -> Vec<u8> {
+    const LEN: u16 = 638;
+    let flags_len = |len: u16| (0x7000 | len).to_be_bytes();
+    let mut packet = Vec::with_capacity(LEN as usize);
+
+    // Root layer
+    packet.extend_from_slice(&0x0010u16.to_be_bytes());
+    packet.extend_from_slice(&0u16.to_be_bytes());
+    packet.extend_from_slice(b"ASC-E1.17\0\0\0");
+    packet.extend_from_slice(&flags_len(LEN - 16));
+    packet.extend_from_slice(&4u32.to_be_bytes()); // VECTOR_ROOT_E131_DATA
+    packet.extend_from_slice(cid);
+
+    // Framing layer
+    packet.extend_from_slice(&flags_len(LEN - 38));
+    packet.extend_from_slice(&2u32.to_be_bytes()); // VECTOR_E131_DATA_PACKET
+    let mut source = [0u8; 64];
+    let name = b"music_visualizer";
+    source[..name.len()].copy_from_slice(name);
+    packet.extend_from_slice(&source);
+    packet.push(100); // priority
+    packet.extend_from_slice(&0u16.to_be_bytes()); // sync address
+    packet.push(sequence);
+    packet.push(0); // options
+    packet.extend_from_slice(&universe.to_be_bytes());
+
+    // DMP layer
+    packet.extend_from_slice(&flags_len(LEN - 115));
+    packet.push(2); // VECTOR_DMP_SET_PROPERTY
+    packet.push(0xA1); // address and data type
+    packet.extend_from_slice(&0u16.to_be_bytes()); // first property address
+    packet.extend_from_slice(&1u16.to_be_bytes()); // address increment
+    packet.extend_from_slice(&513u16.to_be_bytes()); // start code + 512 slots
+    packet.push(0); // DMX start code
+    packet.extend_from_slice(data);
+    packet
+}
+
+/// Drives DMX channels from the analysis according to `[[dmx.channels]]`.
+///
+/// [`update`](Self::update) only fills in the universe; a separate thread transmits
+/// the latest one at the configured refresh rate, whatever the frame rate.
+pub struct DmxOutput {
+    channels: Vec<DmxChannelConfig>,
+    universe: Arc<Mutex<[u8; 512]>>,
+    gains: [AutoGain; 4],
+    beat_decay: f32,
+    beat_level: f32,
+    last_beats: usize,
+    last_update: Instant,
+}
+
+impl DmxOutput {
+    /// Returns `None` when DMX output is switched off.
+    pub fn from_config(config: &DmxConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        if let Some(bad) = config.channels.iter().find(|c| !(1..=512).contains(&c.channel)) {
+            return Err(anyhow!("DMX channel {} is outside 1-512", bad.channel));
+        }
+        if !config.refresh_hz.is_finite() {
+            return Err(anyhow!("[dmx] refresh_hz must be a finite number"));
+        }
+
+        let universe_number = match config.protocol {
+            DmxProtocol::ArtNet => config.universe,
+            // sACN universes are numbered from 1.
+            DmxProtocol::Sacn => config.universe.max(1),
+        };
+        let target: SocketAddr = match &config.target {
+            Some(target) => target
+                .to_socket_addrs()
+                .ok()
+                .and_then(|mut addrs| addrs.next())
+                .ok_or_else(|| anyhow!("invalid DMX target '{}'", target))?,
+            None => match config.protocol {
+                DmxProtocol::ArtNet => SocketAddr::from(([255, 255, 255, 255], 6454)),
+                DmxProtocol::Sacn => {
+                    let [hi, lo] = universe_number.to_be_bytes();
+                    SocketAddr::from(([239, 255, hi, lo], 5568))
+                }
+            },
+        };
+
+        let socket = UdpSocket::bind("0.0.0.0:0").context("failed to open DMX socket")?;
+        socket.set_broadcast(true)?;
+        let universe = Arc::new(Mutex::new([0u8; 512]));
+        let protocol = config.protocol;
+        let interval = Duration::from_secs_f32(1.0 / config.refresh_hz.clamp(1.0, 44.0));
+        let cid: [u8; 16] = rand::random();
+
+        let shared = universe.clone();
+        thread::Builder::new()
+            .name("dmx-output".into())
+            .spawn(move || {
+                let mut sequence = 0u8;
+                loop {
+                    let started = Instant::now();
+                    let data = *shared.lock().unwrap();
+                    // Art-Net treats sequence 0 as "not sequenced", so wrap to 1.
+                    sequence = sequence.wrapping_add(1).max(1);
+                    let packet = match protocol {
+                        DmxProtocol::ArtNet => artnet_packet(universe_number, sequence, &data),
+                        DmxProtocol::Sacn => sacn_packet(universe_number, sequence, &cid, &data),
+                    };
+                    let _ = socket.send_to(&packet, target);
+                    thread::sleep(interval.saturating_sub(started.elapsed()));
+                }
+            })?;
+
+        Ok(Some(Self {
+            channels: config.channels.clone(),
+            universe,
+            gains: [AutoGain::new(), AutoGain::new(), AutoGain::new(), AutoGain::new()],
+            beat_decay: config.beat_decay.max(0.01),
+            beat_level: 0.0,
+            last_beats: 0,
+            last_update: Instant::now(),
+        }))
+    }
+
+    pub fn update(&mut self, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+        let dt = self.last_update.elapsed().as_secs_f32();
+        self.last_update = Instant::now();
+
+        if beat_info.total_beats != self.last_beats {
+            self.last_beats = beat_info.total_beats;
+            self.beat_level = 1.0;
+        } else {
+            self.beat_level = (self.beat_level - dt / self.beat_decay).max(0.0);
+        }
+
+        let bands = Bands::from_spectrum(spectrum);
+        let levels = [
+            self.gains[0].apply(bands.sub, dt),
+            self.gains[1].apply(bands.bass, dt),
+            self.gains[2].apply(bands.mid, dt),
+            self.gains[3].apply(bands.high, dt),
+        ];
+
+        let (peak_freq, _) = get_peak_frequency(spectrum);
+        let hue = if peak_freq > 0 {
+            let midi = 69.0 + 12.0 * (peak_freq as f32 / 440.0).log2();
+            midi.round().rem_euclid(12.0) / 12.0
+        } else {
+            0.0
+        };
+        let (red, green, blue) = hue_to_rgb(hue);
+
+        let mut universe = self.universe.lock().unwrap();
+        for mapping in &self.channels {
+            let value = match mapping.feature {
+                DmxFeature::Beat => self.beat_level,
+                DmxFeature::Sub => levels[0],
+                DmxFeature::Bass => levels[1],
+                DmxFeature::Mid => levels[2],
+                DmxFeature::High => levels[3],
+                DmxFeature::NoteHue => hue,
+                DmxFeature::NoteRed => red,
+                DmxFeature::NoteGreen => green,
+                DmxFeature::NoteBlue => blue,
+            };
+            let value = (value * mapping.gain).clamp(0.0, 1.0);
+            let (min, max) = (mapping.min as f32, mapping.max as f32);
+            universe[mapping.channel as usize - 1] = (min + (max - min) * value).round() as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::compute_spectrum;
+
+    fn universe_with(first: u8, last: u8) -> [u8; 512] {
+        let mut data = [0u8; 512];
+        data[0] = first;
+        data[511] = last;
+        data
+    }
+
+    #[test]
+    fn artnet_layout() {
+        let packet = artnet_packet(0x0123, 7, &universe_with(1, 2));
+        assert_eq!(packet.len(), 18 + 512);
+        assert_eq!(&packet[..8], b"Art-Net\0");
+        assert_eq!(&packet[8..10], &[0x00, 0x50]); // OpDmx, little-endian
+        assert_eq!(&packet[10..12], &[0, 14]);
+        assert_eq!(packet[12], 7);
+        assert_eq!(&packet[14..16], &[0x23, 0x01]); // SubUni, Net
+        assert_eq!(&packet[16..18], &[0x02, 0x00]); // 512 slots, big-endian
+        assert_eq!((packet[18], packet[529]), (1, 2));
+    }
+
+    #[test]
+    fn sacn_layout() {
+        let cid = [9u8; 16];
+        let packet = sacn_packet(3, 42, &cid, &universe_with(1, 2));
+        assert_eq!(packet.len(), 638);
+        assert_eq!(&packet[4..16], b"ASC-E1.17\0\0\0");
+        // Flags and length of each layer, counted from its own start.
+        assert_eq!(&packet[16..18], &(0x7000u16 | 622).to_be_bytes());
+        assert_eq!(&packet[22..38], &cid);
+        assert_eq!(&packet[38..40], &(0x7000u16 | 600).to_be_bytes());
+        assert_eq!(&packet[44..60], b"music_visualizer");
+        assert_eq!(packet[108], 100);
+        assert_eq!(packet[111], 42);
+        assert_eq!(&packet[113..115], &[0, 3]);
+        assert_eq!(&packet[115..117], &(0x7000u16 | 523).to_be_bytes());
+        assert_eq!(&packet[123..125], &513u16.to_be_bytes());
+        assert_eq!(packet[125], 0); // start code
+        assert_eq!((packet[126], packet[637]), (1, 2));
+    }
+
+    /// A sine right on an FFT bin (20 * 48000 / 2048 Hz), the pitch class A#.
+    fn tone() -> FrequencySpectrum {
+        let samples: Vec<f32> = (0..4096)
+            .map(|i| (i as f32 * 468.75 / 48000.0 * std::f32::consts::TAU).sin())
+            .collect();
+        compute_spectrum(&samples, 48000).unwrap()
+    }
+
+    fn mapping(channel: u16, feature: DmxFeature, min: u8, max: u8) -> DmxChannelConfig {
+        DmxChannelConfig {
+            channel,
+            feature,
+            gain: 1.0,
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn mappings_reach_a_local_receiver() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let config = DmxConfig {
+            enabled: true,
+            target: Some(receiver.local_addr().unwrap().to_string()),
+            universe: 1,
+            channels: vec![
+                mapping(1, DmxFeature::Beat, 10, 200),
+                mapping(2, DmxFeature::Mid, 0, 255),
+                mapping(3, DmxFeature::NoteRed, 0, 255),
+                mapping(4, DmxFeature::NoteGreen, 0, 255),
+                mapping(5, DmxFeature::NoteBlue, 0, 255),
+                mapping(512, DmxFeature::Beat, 0, 255),
+            ],
+            ..DmxConfig::default()
+        };
+        let mut output = DmxOutput::from_config(&config).unwrap().unwrap();
+        let beat = BeatInfo {
+            is_beat: true,
+            bpm: 120.0,
+            total_beats: 1,
+        };
+        output.update(&tone(), &beat);
+
+        // Packets sent before the update carry an empty universe; wait for ours.
+        let mut buf = [0u8; 1024];
+        let data = loop {
+            let n = receiver.recv(&mut buf).expect("no DMX packet received");
+            assert_eq!(n, 18 + 512);
+            assert_eq!(&buf[..8], b"Art-Net\0");
+            if buf[18] != 0 {
+                break buf[18..n].to_vec();
+            }
+        };
+        assert_eq!(data[0], 200);
+        assert_eq!(data[1], 255);
+        // A# sits at hue 10/12: magenta.
+        assert_eq!(&data[2..5], &[255, 0, 255]);
+        assert_eq!(data[511], 255);
+        assert!(data[5..511].iter().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn beat_channel_fades_out() {
+        let config = DmxConfig {
+            enabled: true,
+            target: Some("127.0.0.1:9".into()),
+            beat_decay: 0.01,
+            channels: vec![mapping(1, DmxFeature::Beat, 0, 255)],
+            ..DmxConfig::default()
+        };
+        let mut output = DmxOutput::from_config(&config).unwrap().unwrap();
+        let mut beat = BeatInfo {
+            is_beat: true,
+            bpm: 120.0,
+            total_beats: 1,
+        };
+        output.update(&tone(), &beat);
+        assert_eq!(output.universe.lock().unwrap()[0], 255);
+
+        thread::sleep(Duration::from_millis(20));
+        beat.is_beat = false;
+        output.update(&tone(), &beat);
+        assert_eq!(output.universe.lock().unwrap()[0], 0);
+    }
+
+    #[test]
+    fn rejects_channels_outside_the_universe() {
+        let config = DmxConfig {
+            enabled: true,
+            target: Some("127.0.0.1:9".into()),
+            channels: vec![mapping(513, DmxFeature::Bass, 0, 255)],
+            ..DmxConfig::default()
+        };
+        assert!(DmxOutput::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn rejects_a_non_finite_refresh_rate() {
+        let config = DmxConfig {
+            enabled: true,
+            target: Some("127.0.0.1:9".into()),
+            refresh_hz: f32::NAN,
+            ..DmxConfig::default()
+        };
+        assert!(DmxOutput::from_config(&config).is_err());
+    }
+}
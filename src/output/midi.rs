@@ -0,0 +1,395 @@
+use super::AutoGain;
+use crate::{analysis::Bands, config::MidiOutConfig, visualizers::BeatInfo};
+use anyhow::{anyhow, Result};
+use spectrum_analyzer::FrequencySpectrum;
+use std::{
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    time::{Duration, Instant},
+};
+
+/// MIDI clock runs at 24 pulses per quarter note.
+const CLOCK_PPQN: f32 = 24.0;
+
+/// What the main loop hands to the sequencer thread.
+enum Message {
+    /// Estimated tempo; 0 stops the clock.
+    Tempo(f32),
+    /// A detected beat, played as a note with this velocity.
+    Beat(u8),
+    /// New values for the sub, bass, mid and high controllers.
+    Controls([u8; 4]),
+}
+
+/// Settings the sequencer thread needs, copied out of the config.
+struct Settings {
+    port_name: String,
+    /// Zero-based, as sent on the wire.
+    channel: u8,
+    clock: bool,
+    note: u8,
+    note_length: Duration,
+    band_cc: [u8; 4],
+}
+
+impl Settings {
+    /// Checks `config` and copies out what the sequencer thread needs.
+    fn new(config: &MidiOutConfig) -> Result<Self> {
+        if !(1..=16).contains(&config.channel) {
+            return Err(anyhow!("MIDI channel {} is outside 1-16", config.channel));
+        }
+        if let Some(bad) = [config.note, config.velocity]
+            .into_iter()
+            .chain(config.band_cc)
+            .find(|&value| value > 127)
+        {
+            return Err(anyhow!(
+                "MIDI note, velocity and controller numbers must be 0-127, got {}",
+                bad
+            ));
+        }
+        if !config.note_length.is_finite() {
+            return Err(anyhow!("[midi_out] note_length must be a finite number"));
+        }
+
+        Ok(Self {
+            port_name: config.port_name.clone(),
+            channel: config.channel - 1,
+            clock: config.clock,
+            note: config.note,
+            note_length: Duration::from_secs_f32(config.note_length.clamp(0.001, 10.0)),
+            band_cc: config.band_cc,
+        })
+    }
+}
+
+/// Drives synths and DAWs from the analysis through an ALSA sequencer port.
+///
+/// - MIDI clock (24 PPQN) at the detected tempo, with start/stop as it comes and goes
+/// - a note-on for every detected beat, released after `note_length`
+/// - one controller per band (sub, bass, mid, high), 0-127
+///
+/// Timing lives on a separate thread so clock pulses stay even regardless of the
+/// frame rate; [`update`](Self::update) only passes on what changed.
+pub struct MidiOutput {
+    messages: Sender<Message>,
+    gains: [AutoGain; 4],
+    velocity: u8,
+    last_bpm: f32,
+    last_beats: usize,
+    last_controls: [u8; 4],
+    last_update: Instant,
+}
+
+impl MidiOutput {
+    /// Returns `None` when MIDI output is switched off.
+    pub fn from_config(config: &MidiOutConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let settings = Settings::new(config)?;
+        let (messages, receiver) = mpsc::channel();
+        sequencer::spawn(settings, receiver)?;
+
+        Ok(Some(Self {
+            messages,
+            gains: [AutoGain::new(), AutoGain::new(), AutoGain::new(), AutoGain::new()],
+            velocity: config.velocity,
+            last_bpm: 0.0,
+            last_beats: 0,
+            last_controls: [u8::MAX; 4],
+            last_update: Instant::now(),
+        }))
+    }
+
+    pub fn update(&mut self, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+        let dt = self.last_update.elapsed().as_secs_f32();
+        self.last_update = Instant::now();
+
+        // The estimate jitters by fractions of a BPM; only re-time the clock on real changes.
+        if (beat_info.bpm - self.last_bpm).abs() >= 0.5 || (beat_info.bpm == 0.0) != (self.last_bpm == 0.0) {
+            self.last_bpm = beat_info.bpm;
+            let _ = self.messages.send(Message::Tempo(beat_info.bpm));
+        }
+        if beat_info.total_beats != self.last_beats {
+            self.last_beats = beat_info.total_beats;
+            let _ = self.messages.send(Message::Beat(self.velocity));
+        }
+
+        let bands = Bands::from_spectrum(spectrum);
+        let mut controls = [0u8; 4];
+        for ((control, gain), value) in controls
+            .iter_mut()
+            .zip(&mut self.gains)
+            .zip([bands.sub, bands.bass, bands.mid, bands.high])
+        {
+            *control = (gain.apply(value, dt) * 127.0).round() as u8;
+        }
+        if controls != self.last_controls {
+            self.last_controls = controls;
+            let _ = self.messages.send(Message::Controls(controls));
+        }
+    }
+}
+
+/// Time between clock pulses at `bpm`.
+fn clock_interval(bpm: f32) -> Duration {
+    Duration::from_secs_f32(60.0 / (bpm * CLOCK_PPQN))
+}
+
+/// Sequencer-independent scheduling: waits for messages and clock ticks, calling
+/// `send` with the raw MIDI bytes to emit. Returns when the main loop goes away.
+fn run(
+    settings: &Settings,
+    messages: mpsc::Receiver<Message>,
+    mut send: impl FnMut(&[u8]),
+) {
+    let channel = settings.channel;
+    let mut interval: Option<Duration> = None;
+    let mut next_clock = Instant::now();
+    let mut running = false;
+    let mut note_off: Option<Instant> = None;
+
+    loop {
+        let deadline = [interval.map(|_| next_clock), note_off].into_iter().flatten().min();
+        let message = match deadline {
+            Some(deadline) => {
+                messages.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => messages.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match message {
+            Ok(Message::Tempo(bpm)) if bpm > 0.0 && settings.clock => {
+                if interval.is_none() {
+                    next_clock = Instant::now();
+                }
+                interval = Some(clock_interval(bpm));
+            }
+            Ok(Message::Tempo(_)) => {
+                interval = None;
+                if running {
+                    running = false;
+                    send(&[0xFC]);
+                }
+            }
+            Ok(Message::Beat(velocity)) => {
+                if note_off.is_some() {
+                    send(&[0x80 | channel, settings.note, 0]);
+                }
+                send(&[0x90 | channel, settings.note, velocity]);
+                note_off = Some(Instant::now() + settings.note_length);
+            }
+            Ok(Message::Controls(values)) => {
+                for (cc, value) in settings.band_cc.iter().zip(values) {
+                    send(&[0xB0 | channel, *cc, value]);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                if note_off.is_some() {
+                    send(&[0x80 | channel, settings.note, 0]);
+                }
+                if running {
+                    send(&[0xFC]);
+                }
+                return;
+            }
+        }
+
+        let now = Instant::now();
+        if note_off.is_some_and(|at| now >= at) {
+            note_off = None;
+            send(&[0x80 | channel, settings.note, 0]);
+        }
+        if let Some(interval) = interval
+            && now >= next_clock
+        {
+            if !running {
+                running = true;
+                send(&[0xFA]);
+            }
+            send(&[0xF8]);
+            next_clock += interval;
+            // After a stall, carry on from now rather than bursting to catch up.
+            if next_clock < now {
+                next_clock = now + interval;
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sequencer {
+    use super::{run, Message, Settings};
+    use alsa::seq::{EvCtrl, EvNote, EvQueueControl, Event, EventType, PortCap, PortType, Seq};
+    use anyhow::{Context, Result};
+    use std::{ffi::CString, sync::mpsc::Receiver, thread};
+
+    /// Opens a sequencer client with one readable port that anything can subscribe
+    /// to (e.g. `aconnect music-visualizer:0 <synth>`), and feeds it from `messages`.
+    pub(super) fn spawn(settings: Settings, messages: Receiver<Message>) -> Result<()> {
+        let seq = Seq::open(None, Some(alsa::Direction::Playback), false)
+            .context("failed to open the ALSA sequencer")?;
+        let name = CString::new(settings.port_name.as_str()).context("invalid MIDI port name")?;
+        seq.set_client_name(&name)?;
+        let port = seq
+            .create_simple_port(
+                &name,
+                PortCap::READ | PortCap::SUBS_READ,
+                PortType::MIDI_GENERIC | PortType::APPLICATION,
+            )
+            .context("failed to create MIDI port")?;
+
+        thread::Builder::new()
+            .name("midi-output".into())
+            .spawn(move || {
+                run(&settings, messages, |bytes| {
+                    let mut event = match *bytes {
+                        [0xF8] => Event::new(EventType::Clock, &EvQueueControl { queue: 0, value: () }),
+                        [0xFA] => Event::new(EventType::Start, &EvQueueControl { queue: 0, value: () }),
+                        [0xFC] => Event::new(EventType::Stop, &EvQueueControl { queue: 0, value: () }),
+                        [status, note, velocity] if status & 0xE0 == 0x80 => Event::new(
+                            if status & 0xF0 == 0x90 { EventType::Noteon } else { EventType::Noteoff },
+                            &EvNote {
+                                channel: status & 0x0F,
+                                note,
+                                velocity,
+                                ..Default::default()
+                            },
+                        ),
+                        [status, param, value] => Event::new(
+                            EventType::Controller,
+                            &EvCtrl {
+                                channel: status & 0x0F,
+                                param: param.into(),
+                                value: value.into(),
+                            },
+                        ),
+                        _ => return,
+                    };
+                    event.set_source(port);
+                    event.set_subs();
+                    event.set_direct();
+                    // Fails only when nobody is subscribed or the pool is full; both are harmless.
+                    let _ = seq.event_output_direct(&mut event);
+                });
+            })?;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sequencer {
+    use super::{Message, Settings};
+    use anyhow::{anyhow, Result};
+    use std::sync::mpsc::Receiver;
+
+    pub(super) fn spawn(_settings: Settings, _messages: Receiver<Message>) -> Result<()> {
+        Err(anyhow!("MIDI output needs the ALSA sequencer, which is only available on Linux"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn settings(clock: bool) -> Settings {
+        Settings {
+            port_name: "test".into(),
+            channel: 1,
+            clock,
+            note: 36,
+            note_length: Duration::from_millis(10),
+            band_cc: [20, 21, 22, 23],
+        }
+    }
+
+    /// Runs the scheduler over `messages` until they run out, collecting what it sends.
+    fn emitted(settings: &Settings, messages: Vec<Message>) -> Vec<Vec<u8>> {
+        let (tx, rx) = mpsc::channel();
+        for message in messages {
+            tx.send(message).unwrap();
+        }
+        drop(tx);
+        let mut sent = Vec::new();
+        run(settings, rx, |bytes| sent.push(bytes.to_vec()));
+        sent
+    }
+
+    #[test]
+    fn clock_starts_with_a_tempo_and_stops_without_one() {
+        let sent = emitted(&settings(true), vec![Message::Tempo(120.0), Message::Tempo(0.0)]);
+        assert_eq!(sent, [vec![0xFA], vec![0xF8], vec![0xFC]]);
+    }
+
+    #[test]
+    fn no_clock_when_disabled() {
+        let sent = emitted(&settings(false), vec![Message::Tempo(120.0), Message::Tempo(0.0)]);
+        assert!(sent.is_empty());
+    }
+
+    #[test]
+    fn clock_pulses_at_24_per_beat() {
+        let close = |bpm: f32, expected: Duration| {
+            let interval = clock_interval(bpm);
+            assert!(interval.abs_diff(expected) < Duration::from_micros(1), "{:?}", interval);
+        };
+        close(120.0, Duration::from_secs_f64(0.5 / 24.0));
+        close(60.0, Duration::from_secs_f64(1.0 / 24.0));
+        close(300.0, Duration::from_secs_f64(0.2 / 24.0));
+    }
+
+    #[test]
+    fn rejects_a_non_finite_note_length() {
+        let config = MidiOutConfig {
+            enabled: true,
+            note_length: f32::NAN,
+            ..MidiOutConfig::default()
+        };
+        assert!(Settings::new(&config).is_err());
+    }
+
+    #[test]
+    fn beat_plays_a_note_and_releases_it() {
+        let (tx, rx) = mpsc::channel();
+        tx.send(Message::Beat(100)).unwrap();
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            drop(tx);
+        });
+        let mut sent = Vec::new();
+        run(&settings(true), rx, |bytes| sent.push(bytes.to_vec()));
+        sender.join().unwrap();
+        // Released by the timer, not again on shutdown.
+        assert_eq!(sent, [vec![0x91, 36, 100], vec![0x81, 36, 0]]);
+    }
+
+    #[test]
+    fn a_new_beat_releases_the_previous_note_first() {
+        let sent = emitted(&settings(true), vec![Message::Beat(100), Message::Beat(90)]);
+        assert_eq!(
+            sent,
+            [
+                vec![0x91, 36, 100],
+                vec![0x81, 36, 0],
+                vec![0x91, 36, 90],
+                vec![0x81, 36, 0],
+            ]
+        );
+    }
+
+    #[test]
+    fn controls_go_out_on_the_band_controllers() {
+        let sent = emitted(&settings(true), vec![Message::Controls([1, 2, 3, 127])]);
+        assert_eq!(
+            sent,
+            [
+                vec![0xB1, 20, 1],
+                vec![0xB1, 21, 2],
+                vec![0xB1, 22, 3],
+                vec![0xB1, 23, 127],
+            ]
+        );
+    }
+}
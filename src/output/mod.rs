@@ -0,0 +1,95 @@
+//! Sinks that send the analysis somewhere other than the terminal.
+
+pub mod dmx;
+pub mod midi;
+pub mod osc;
+pub mod websocket;
+pub mod wled;
+
+use crate::{config::Config, visualizers::BeatInfo};
+use anyhow::Result;
+use dmx::DmxOutput;
+use midi::MidiOutput;
+use osc::OscSender;
+use spectrum_analyzer::FrequencySpectrum;
+use websocket::WebSocketServer;
+use wled::WledOutput;
+
+/// Scales a band's energy against its own recent peak, so mappings work at any volume.
+pub struct AutoGain {
+    peak: f32,
+}
+
+impl AutoGain {
+    pub fn new() -> Self {
+        Self { peak: 1e-6 }
+    }
+
+    pub fn apply(&mut self, value: f32, dt: f32) -> f32 {
+        // The reference peak halves roughly every four seconds of quieter input.
+        self.peak = (self.peak * 0.5f32.powf(dt / 4.0)).max(value).max(1e-6);
+        (value / self.peak).clamp(0.0, 1.0)
+    }
+}
+
+/// Fully saturated RGB (0..1) for a hue in 0..1.
+pub fn hue_to_rgb(hue: f32) -> (f32, f32, f32) {
+    let h = hue.rem_euclid(1.0) * 6.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    match h as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    }
+}
+
+/// Every enabled output, fed once per analysis step.
+#[derive(Default)]
+pub struct Outputs {
+    osc: Option<OscSender>,
+    websocket: Option<WebSocketServer>,
+    dmx: Option<DmxOutput>,
+    wled: Option<WledOutput>,
+    midi: Option<MidiOutput>,
+}
+
+impl Outputs {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Self {
+            osc: OscSender::from_config(&config.osc)?,
+            websocket: WebSocketServer::from_config(&config.websocket)?,
+            dmx: DmxOutput::from_config(&config.dmx)?,
+            wled: WledOutput::from_config(&config.wled)?,
+            midi: MidiOutput::from_config(&config.midi_out)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.osc.is_none()
+            && self.websocket.is_none()
+            && self.dmx.is_none()
+            && self.wled.is_none()
+            && self.midi.is_none()
+    }
+
+    pub fn update(&mut self, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+        if let Some(osc) = &mut self.osc {
+            osc.send(spectrum, beat_info);
+        }
+        if let Some(websocket) = &mut self.websocket {
+            websocket.publish(spectrum, beat_info);
+        }
+        if let Some(dmx) = &mut self.dmx {
+            dmx.update(spectrum, beat_info);
+        }
+        if let Some(wled) = &mut self.wled {
+            wled.update(spectrum, beat_info);
+        }
+        if let Some(midi) = &mut self.midi {
+            midi.update(spectrum, beat_info);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    analysis::{get_peak_frequency, log_bins, Bands},
+    config::OscConfig,
+    visualizers::BeatInfo,
+};
+use anyhow::{Context, Result};
+use spectrum_analyzer::FrequencySpectrum;
+use std::net::UdpSocket;
+
+/// One OSC argument.
+enum Arg {
+    Int(i32),
+    Float(f32),
+}
+
+/// Appends `s` as an OSC string: NUL-terminated and padded to a multiple of four bytes.
+fn push_str(packet: &mut Vec<u8>, s: &str) {
+    packet.extend_from_slice(s.as_bytes());
+    let pad = 4 - s.len() % 4;
+    packet.extend(std::iter::repeat_n(0, pad));
+}
+
+/// Encodes an OSC 1.0 message.
+fn encode(address: &str, args: &[Arg]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(address.len() + 8 + args.len() * 5);
+    push_str(&mut packet, address);
+    let tags: String = std::iter::once(',')
+        .chain(args.iter().map(|arg| match arg {
+            Arg::Int(_) => 'i',
+            Arg::Float(_) => 'f',
+        }))
+        .collect();
+    push_str(&mut packet, &tags);
+    for arg in args {
+        match arg {
+            Arg::Int(v) => packet.extend_from_slice(&v.to_be_bytes()),
+            Arg::Float(v) => packet.extend_from_slice(&v.to_be_bytes()),
+        }
+    }
+    packet
+}
+
+/// Publishes the analysis of every frame as OSC messages over UDP.
+///
+/// - `/beat i` with the running beat count, once per detected beat
+/// - `/bpm f`
+/// - `/bands/sub`, `/bands/bass`, `/bands/mid`, `/bands/high` `f`
+/// - `/peak_freq f` in Hz
+/// - `/spectrum f...` with `spectrum_bins` log-spaced magnitudes, low to high
+pub struct OscSender {
+    socket: UdpSocket,
+    spectrum_bins: usize,
+    last_beats: usize,
+}
+
+impl OscSender {
+    /// Returns `None` when OSC output is switched off.
+    pub fn from_config(config: &OscConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let socket = UdpSocket::bind("0.0.0.0:0").context("failed to open OSC socket")?;
+        socket
+            .connect(&config.target)
+            .with_context(|| format!("invalid OSC target '{}'", config.target))?;
+        // A slow or missing receiver must never hold up the render loop.
+        socket.set_nonblocking(true)?;
+        Ok(Some(Self {
+            socket,
+            spectrum_bins: config.spectrum_bins,
+            last_beats: 0,
+        }))
+    }
+
+    pub fn send(&mut self, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+        let bands = Bands::from_spectrum(spectrum);
+        let (peak_freq, _) = get_peak_frequency(spectrum);
+
+        let mut messages = vec![
+            encode("/bpm", &[Arg::Float(beat_info.bpm)]),
+            encode("/bands/sub", &[Arg::Float(bands.sub)]),
+            encode("/bands/bass", &[Arg::Float(bands.bass)]),
+            encode("/bands/mid", &[Arg::Float(bands.mid)]),
+            encode("/bands/high", &[Arg::Float(bands.high)]),
+            encode("/peak_freq", &[Arg::Float(peak_freq as f32)]),
+        ];
+        if beat_info.total_beats != self.last_beats {
+            self.last_beats = beat_info.total_beats;
+            messages.insert(0, encode("/beat", &[Arg::Int(beat_info.total_beats as i32)]));
+        }
+        if self.spectrum_bins > 0 {
+            let bins: Vec<Arg> = log_bins(spectrum, self.spectrum_bins)
+                .into_iter()
+                .map(Arg::Float)
+                .collect();
+            messages.push(encode("/spectrum", &bins));
+        }
+
+        // Send errors (nobody listening, buffer full) are dropped like lost datagrams.
+        for message in messages {
+            let _ = self.socket.send(&message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::compute_spectrum;
+    use std::time::Duration;
+
+    #[test]
+    fn strings_are_nul_terminated_and_padded() {
+        let mut packet = Vec::new();
+        push_str(&mut packet, "/bpm");
+        assert_eq!(packet, b"/bpm\0\0\0\0");
+
+        packet.clear();
+        push_str(&mut packet, "/abc");
+        assert_eq!(packet.len(), 8);
+        packet.clear();
+        push_str(&mut packet, "/ab");
+        assert_eq!(packet, b"/ab\0");
+    }
+
+    #[test]
+    fn encodes_type_tags_and_big_endian_arguments() {
+        let packet = encode("/x", &[Arg::Int(7), Arg::Float(1.5)]);
+        let mut expected = b"/x\0\0,if\0".to_vec();
+        expected.extend_from_slice(&7i32.to_be_bytes());
+        expected.extend_from_slice(&1.5f32.to_be_bytes());
+        assert_eq!(packet, expected);
+        assert_eq!(packet.len() % 4, 0);
+    }
+
+    #[test]
+    fn message_without_arguments_has_only_a_comma_tag() {
+        assert_eq!(encode("/ping", &[]), b"/ping\0\0\0,\0\0\0");
+    }
+
+    #[test]
+    fn sends_to_a_local_receiver() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let config = OscConfig {
+            enabled: true,
+            target: receiver.local_addr().unwrap().to_string(),
+            spectrum_bins: 4,
+        };
+        let mut sender = OscSender::from_config(&config).unwrap().unwrap();
+
+        let samples: Vec<f32> = (0..4096)
+            .map(|i| (i as f32 * 440.0 / 48000.0 * std::f32::consts::TAU).sin())
+            .collect();
+        let spectrum = compute_spectrum(&samples, 48000).unwrap();
+        let beat_info = BeatInfo {
+            is_beat: true,
+            bpm: 120.0,
+            total_beats: 3,
+        };
+        sender.send(&spectrum, &beat_info);
+
+        let mut buf = [0u8; 1024];
+        let mut received = Vec::new();
+        while let Ok(n) = receiver.recv(&mut buf) {
+            received.push(buf[..n].to_vec());
+            if received.len() == 8 {
+                break;
+            }
+        }
+        assert_eq!(received.len(), 8);
+        assert_eq!(received[0], encode("/beat", &[Arg::Int(3)]));
+        assert_eq!(received[1], encode("/bpm", &[Arg::Float(120.0)]));
+        let spectrum_message = received.last().unwrap();
+        assert!(spectrum_message.starts_with(b"/spectrum\0\0\0,ffff\0\0\0"));
+        assert_eq!(spectrum_message.len(), 12 + 8 + 4 * 4);
+    }
+}
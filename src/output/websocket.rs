@@ -0,0 +1,277 @@
+use crate::{
+    analysis::{get_peak_frequency, log_bins, Bands},
+    clock,
+    config::WebSocketConfig,
+    visualizers::BeatInfo,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use spectrum_analyzer::FrequencySpectrum;
+use std::{
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tungstenite::Message;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebSocketFormat {
+    /// One JSON object per frame, as text messages.
+    Json,
+    /// Compact little-endian binary messages.
+    Binary,
+}
+
+#[derive(Serialize)]
+struct JsonBands {
+    sub: f32,
+    bass: f32,
+    mid: f32,
+    high: f32,
+}
+
+/// One analysis frame, serialized as is in `json` mode.
+#[derive(Serialize)]
+struct Frame<'a> {
+    /// Seconds since startup.
+    time: f32,
+    /// True on the frame a new beat was detected.
+    beat: bool,
+    total_beats: usize,
+    bpm: f32,
+    peak_freq: u32,
+    bands: JsonBands,
+    spectrum: &'a [f32],
+}
+
+/// Pushes analysis frames to every connected WebSocket client.
+///
+/// Accepting connections and writing to sockets happen on background threads; the
+/// render loop only hands each client's queue a ready-made message. A client whose
+/// queue is full (it isn't reading fast enough) skips frames instead of slowing
+/// anyone else down.
+///
+/// In `binary` mode each frame is little-endian: `u32` total beats, `u32` flags
+/// (bit 0 = beat on this frame), then `f32` BPM, peak frequency, sub, bass, mid and
+/// high, a `u32` bin count and that many `f32` spectrum bins.
+///
+/// At most `max_clients` connections are served at once, counting those still in
+/// the handshake; any more are closed straight away.
+pub struct WebSocketServer {
+    clients: Arc<Mutex<Vec<SyncSender<Message>>>>,
+    format: WebSocketFormat,
+    spectrum_bins: usize,
+    last_beats: usize,
+}
+
+impl WebSocketServer {
+    /// Returns `None` when the server is switched off.
+    pub fn from_config(config: &WebSocketConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let listener = TcpListener::bind(&config.bind)
+            .with_context(|| format!("failed to listen for WebSocket clients on {}", config.bind))?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let queue = config.queue.max(1);
+        let max_clients = config.max_clients;
+        let connected = Arc::new(AtomicUsize::new(0));
+
+        let accept_clients = clients.clone();
+        thread::Builder::new()
+            .name("websocket-accept".into())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if connected.load(Ordering::SeqCst) >= max_clients {
+                        continue;
+                    }
+                    let connection = Connection::new(&connected);
+                    let clients = accept_clients.clone();
+                    let _ = thread::Builder::new()
+                        .name("websocket-client".into())
+                        .spawn(move || {
+                            serve_client(stream, &clients, queue);
+                            drop(connection);
+                        });
+                }
+            })?;
+
+        Ok(Some(Self {
+            clients,
+            format: config.format,
+            spectrum_bins: config.spectrum_bins,
+            last_beats: 0,
+        }))
+    }
+
+    pub fn publish(&mut self, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+        let beat = beat_info.total_beats != self.last_beats;
+        self.last_beats = beat_info.total_beats;
+
+        let mut clients = self.clients.lock().unwrap();
+        if clients.is_empty() {
+            return;
+        }
+
+        let bands = Bands::from_spectrum(spectrum);
+        let (peak_freq, _) = get_peak_frequency(spectrum);
+        let bins = log_bins(spectrum, self.spectrum_bins);
+
+        let frame = Frame {
+            time: clock::now().as_secs_f32(),
+            beat,
+            total_beats: beat_info.total_beats,
+            bpm: beat_info.bpm,
+            peak_freq,
+            bands: JsonBands {
+                sub: bands.sub,
+                bass: bands.bass,
+                mid: bands.mid,
+                high: bands.high,
+            },
+            spectrum: &bins,
+        };
+        let Some(message) = encode(self.format, &frame) else {
+            return;
+        };
+
+        // Drop frames for clients that are behind, and forget the ones that left.
+        clients.retain(|client| match client.try_send(message.clone()) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+/// `frame` as a message in `format`.
+fn encode(format: WebSocketFormat, frame: &Frame) -> Option<Message> {
+    match format {
+        WebSocketFormat::Json => serde_json::to_string(frame).ok().map(Message::text),
+        WebSocketFormat::Binary => {
+            let mut data = Vec::with_capacity(36 + frame.spectrum.len() * 4);
+            data.extend_from_slice(&(frame.total_beats as u32).to_le_bytes());
+            data.extend_from_slice(&(frame.beat as u32).to_le_bytes());
+            for value in [
+                frame.bpm,
+                frame.peak_freq as f32,
+                frame.bands.sub,
+                frame.bands.bass,
+                frame.bands.mid,
+                frame.bands.high,
+            ] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data.extend_from_slice(&(frame.spectrum.len() as u32).to_le_bytes());
+            for bin in frame.spectrum {
+                data.extend_from_slice(&bin.to_le_bytes());
+            }
+            Some(Message::binary(data))
+        }
+    }
+}
+
+/// One open connection, counted against `max_clients` until dropped.
+struct Connection(Arc<AtomicUsize>);
+
+impl Connection {
+    fn new(connected: &Arc<AtomicUsize>) -> Self {
+        connected.fetch_add(1, Ordering::SeqCst);
+        Self(connected.clone())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Completes the handshake, then forwards frames until the client goes away. The
+/// client only starts receiving frames once the handshake has succeeded.
+fn serve_client(stream: TcpStream, clients: &Mutex<Vec<SyncSender<Message>>>, queue: usize) {
+    let _ = stream.set_nodelay(true);
+    // A client that never finishes the handshake, or stops reading altogether, is
+    // dropped rather than kept forever.
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let _ = stream.set_write_timeout(Some(Duration::from_secs(5)));
+    let Ok(mut socket) = tungstenite::accept(stream) else {
+        return;
+    };
+    let (tx, frames) = mpsc::sync_channel(queue);
+    clients.lock().unwrap().push(tx);
+    // Returning drops `frames`, which tells the publisher this client is gone.
+    while let Ok(message) = frames.recv() {
+        if socket.send(message).is_err() {
+            return;
+        }
+    }
+    let _ = socket.close(None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn frame(spectrum: &[f32]) -> Frame<'_> {
+        Frame {
+            time: 1.5,
+            beat: true,
+            total_beats: 7,
+            bpm: 120.0,
+            peak_freq: 440,
+            bands: JsonBands {
+                sub: 0.25,
+                bass: 0.5,
+                mid: 0.75,
+                high: 1.0,
+            },
+            spectrum,
+        }
+    }
+
+    #[test]
+    fn json_frames_follow_the_schema() {
+        let Some(Message::Text(text)) = encode(WebSocketFormat::Json, &frame(&[0.5, 2.0])) else {
+            panic!("expected a text message");
+        };
+        let value: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "time": 1.5,
+                "beat": true,
+                "total_beats": 7,
+                "bpm": 120.0,
+                "peak_freq": 440,
+                "bands": { "sub": 0.25, "bass": 0.5, "mid": 0.75, "high": 1.0 },
+                "spectrum": [0.5, 2.0],
+            })
+        );
+    }
+
+    #[test]
+    fn binary_frames_are_little_endian() {
+        let Some(Message::Binary(data)) = encode(WebSocketFormat::Binary, &frame(&[0.5, 2.0]))
+        else {
+            panic!("expected a binary message");
+        };
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&7u32.to_le_bytes());
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        for value in [120.0f32, 440.0, 0.25, 0.5, 0.75, 1.0] {
+            expected.extend_from_slice(&value.to_le_bytes());
+        }
+        expected.extend_from_slice(&2u32.to_le_bytes());
+        expected.extend_from_slice(&0.5f32.to_le_bytes());
+        expected.extend_from_slice(&2.0f32.to_le_bytes());
+        assert_eq!(&data[..], &expected[..]);
+        assert_eq!(data.len(), 36 + 2 * 4);
+    }
+}
@@ -0,0 +1,344 @@
+use super::{hue_to_rgb, AutoGain};
+use crate::{analysis::log_bins, config::WledConfig, visualizers::BeatInfo};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use spectrum_analyzer::FrequencySpectrum;
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// WLED realtime UDP protocols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WledProtocol {
+    /// DRGB up to 490 pixels, DNRGB for longer strips.
+    Auto,
+    /// Index + RGB per pixel, up to 255 pixels.
+    Warls,
+    /// RGB for every pixel from the first, up to 490 pixels.
+    Drgb,
+    /// RGB with a start index, split over several packets for long strips.
+    Dnrgb,
+}
+
+/// What is drawn on the LEDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WledEffect {
+    /// Spectrum bars: on a strip each pixel is one band lit by its level, on a
+    /// matrix each column is a bar growing from the bottom.
+    Bars,
+    /// Rainbow across the strip with each pixel's brightness following its band.
+    Spectrum,
+    /// Every pixel flashes on each beat and fades out.
+    BeatFlash,
+}
+
+const DRGB_MAX: usize = 490;
+const DNRGB_MAX: usize = 489;
+const WARLS_MAX: usize = 255;
+/// DNRGB start indices are 16-bit.
+const PIXELS_MAX: usize = u16::MAX as usize;
+
+/// Physical layout of the LEDs and how logical pixels map onto them.
+#[derive(Clone, Copy)]
+struct Layout {
+    width: usize,
+    height: usize,
+    serpentine: bool,
+    reverse: bool,
+}
+
+impl Layout {
+    fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// LED index of logical pixel (`x`, `y`), with `y` = 0 at the bottom.
+    fn index(&self, x: usize, y: usize) -> usize {
+        let x = if self.serpentine && y % 2 == 1 {
+            self.width - 1 - x
+        } else {
+            x
+        };
+        let i = y * self.width + x;
+        if self.reverse {
+            self.len() - 1 - i
+        } else {
+            i
+        }
+    }
+}
+
+/// Mirrors the analysis onto an addressable LED strip or matrix driven by WLED.
+///
+/// Like the DMX output, [`update`](Self::update) only renders into a shared pixel
+/// buffer; a separate thread sends it at a fixed rate.
+pub struct WledOutput {
+    layout: Layout,
+    effect: WledEffect,
+    brightness: f32,
+    pixels: Arc<Mutex<Vec<[u8; 3]>>>,
+    gains: Vec<AutoGain>,
+    flash: f32,
+    hue: f32,
+    last_beats: usize,
+    last_update: Instant,
+}
+
+impl WledOutput {
+    /// Returns `None` when WLED output is switched off.
+    pub fn from_config(config: &WledConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let layout = if config.width > 0 && config.height > 0 {
+            Layout {
+                width: config.width,
+                height: config.height,
+                serpentine: config.serpentine,
+                reverse: config.reverse,
+            }
+        } else {
+            Layout {
+                width: config.pixels,
+                height: 1,
+                serpentine: false,
+                reverse: config.reverse,
+            }
+        };
+        let len = layout.width.saturating_mul(layout.height);
+        if len == 0 {
+            return Err(anyhow!("[wled] needs `pixels`, or `width` and `height`"));
+        }
+        if len > PIXELS_MAX {
+            return Err(anyhow!("WLED supports at most {} pixels", PIXELS_MAX));
+        }
+        if !config.fps.is_finite() {
+            return Err(anyhow!("[wled] fps must be a finite number"));
+        }
+
+        let protocol = match config.protocol {
+            WledProtocol::Auto if layout.len() <= DRGB_MAX => WledProtocol::Drgb,
+            WledProtocol::Auto => WledProtocol::Dnrgb,
+            WledProtocol::Warls if layout.len() > WARLS_MAX => {
+                return Err(anyhow!("WARLS supports at most {} pixels", WARLS_MAX));
+            }
+            WledProtocol::Drgb if layout.len() > DRGB_MAX => {
+                return Err(anyhow!("DRGB supports at most {} pixels; use dnrgb", DRGB_MAX));
+            }
+            protocol => protocol,
+        };
+
+        let target: SocketAddr = config
+            .target
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| anyhow!("invalid WLED target '{}'", config.target))?;
+        let socket = UdpSocket::bind("0.0.0.0:0").context("failed to open WLED socket")?;
+        let pixels = Arc::new(Mutex::new(vec![[0u8; 3]; layout.len()]));
+        let interval = Duration::from_secs_f32(1.0 / config.fps.clamp(1.0, 120.0));
+        let timeout = config.timeout.clamp(1, 255);
+
+        let shared = pixels.clone();
+        thread::Builder::new()
+            .name("wled-output".into())
+            .spawn(move || loop {
+                let started = Instant::now();
+                let frame = shared.lock().unwrap().clone();
+                for packet in packets(protocol, timeout, &frame) {
+                    let _ = socket.send_to(&packet, target);
+                }
+                thread::sleep(interval.saturating_sub(started.elapsed()));
+            })?;
+
+        Ok(Some(Self {
+            layout,
+            effect: config.effect,
+            brightness: config.brightness.clamp(0.0, 1.0),
+            pixels,
+            gains: (0..layout.width).map(|_| AutoGain::new()).collect(),
+            flash: 0.0,
+            hue: 0.0,
+            last_beats: 0,
+            last_update: Instant::now(),
+        }))
+    }
+
+    pub fn update(&mut self, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+        let dt = self.last_update.elapsed().as_secs_f32();
+        self.last_update = Instant::now();
+        if beat_info.total_beats != self.last_beats {
+            self.last_beats = beat_info.total_beats;
+            self.flash = 1.0;
+            // Each beat moves the flash color a little round the wheel.
+            self.hue = (self.hue + 0.13) % 1.0;
+        } else {
+            self.flash = (self.flash - dt * 4.0).max(0.0);
+        }
+
+        let Layout { width, height, .. } = self.layout;
+        let levels: Vec<f32> = log_bins(spectrum, width)
+            .into_iter()
+            .zip(self.gains.iter_mut())
+            .map(|(value, gain)| gain.apply(value, dt))
+            .collect();
+
+        let mut frame = vec![[0u8; 3]; self.layout.len()];
+        let mut set = |x: usize, y: usize, (r, g, b): (f32, f32, f32), level: f32| {
+            let scale = level.clamp(0.0, 1.0) * self.brightness * 255.0;
+            frame[self.layout.index(x, y)] = [
+                (r * scale).round() as u8,
+                (g * scale).round() as u8,
+                (b * scale).round() as u8,
+            ];
+        };
+
+        for (x, &level) in levels.iter().enumerate() {
+            let position = x as f32 / width.max(2).saturating_sub(1) as f32;
+            for y in 0..height {
+                match self.effect {
+                    WledEffect::Bars if height == 1 => {
+                        // Low bands red through to high bands blue.
+                        set(x, y, hue_to_rgb(position * 0.7), level);
+                    }
+                    WledEffect::Bars => {
+                        let lit = level * height as f32;
+                        if (y as f32) < lit {
+                            // Green at the bottom to red at the top.
+                            let color = hue_to_rgb(0.33 * (1.0 - y as f32 / height as f32));
+                            set(x, y, color, (lit - y as f32).min(1.0));
+                        }
+                    }
+                    WledEffect::Spectrum => {
+                        set(x, y, hue_to_rgb(position), 0.15 + 0.85 * level);
+                    }
+                    WledEffect::BeatFlash => {
+                        set(x, y, hue_to_rgb(self.hue), self.flash);
+                    }
+                }
+            }
+        }
+
+        *self.pixels.lock().unwrap() = frame;
+    }
+}
+
+/// Encodes one frame as the UDP packets of `protocol`.
+fn packets(protocol: WledProtocol, timeout: u8, frame: &[[u8; 3]]) -> Vec<Vec<u8>> {
+    match protocol {
+        WledProtocol::Warls => {
+            let mut packet = vec![1, timeout];
+            for (i, rgb) in frame.iter().enumerate().take(WARLS_MAX) {
+                packet.push(i as u8);
+                packet.extend_from_slice(rgb);
+            }
+            vec![packet]
+        }
+        WledProtocol::Drgb => {
+            let mut packet = vec![2, timeout];
+            packet.extend(frame.iter().take(DRGB_MAX).flatten());
+            vec![packet]
+        }
+        WledProtocol::Auto | WledProtocol::Dnrgb => frame
+            .chunks(DNRGB_MAX)
+            .enumerate()
+            .map(|(chunk, pixels)| {
+                let start = (chunk * DNRGB_MAX) as u16;
+                let mut packet = vec![4, timeout];
+                packet.extend_from_slice(&start.to_be_bytes());
+                packet.extend(pixels.iter().flatten());
+                packet
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(width: usize, height: usize, serpentine: bool, reverse: bool) -> Layout {
+        Layout {
+            width,
+            height,
+            serpentine,
+            reverse,
+        }
+    }
+
+    #[test]
+    fn layout_index() {
+        let plain = layout(4, 3, false, false);
+        assert_eq!(plain.index(0, 0), 0);
+        assert_eq!(plain.index(3, 0), 3);
+        assert_eq!(plain.index(1, 2), 9);
+
+        // Odd rows run right to left.
+        let serpentine = layout(4, 3, true, false);
+        assert_eq!(serpentine.index(0, 0), 0);
+        assert_eq!(serpentine.index(0, 1), 7);
+        assert_eq!(serpentine.index(3, 1), 4);
+        assert_eq!(serpentine.index(1, 2), 9);
+
+        let reversed = layout(4, 3, true, true);
+        assert_eq!(reversed.index(0, 0), 11);
+        assert_eq!(reversed.index(0, 1), 4);
+        assert_eq!(reversed.index(3, 2), 0);
+    }
+
+    fn frame(len: usize) -> Vec<[u8; 3]> {
+        (0..len).map(|i| [i as u8, (i >> 8) as u8, 7]).collect()
+    }
+
+    #[test]
+    fn warls_packet() {
+        let packets = packets(WledProtocol::Warls, 2, &frame(3));
+        assert_eq!(packets, [vec![1, 2, 0, 0, 0, 7, 1, 1, 0, 7, 2, 2, 0, 7]]);
+    }
+
+    #[test]
+    fn drgb_packet() {
+        let packets = packets(WledProtocol::Drgb, 5, &frame(2));
+        assert_eq!(packets, [vec![2, 5, 0, 0, 7, 1, 0, 7]]);
+    }
+
+    #[test]
+    fn dnrgb_splits_long_strips() {
+        let frame = frame(DNRGB_MAX * 2 + 1);
+        let packets = packets(WledProtocol::Dnrgb, 1, &frame);
+        assert_eq!(packets.len(), 3);
+        for (chunk, packet) in packets.iter().enumerate() {
+            let start = chunk * DNRGB_MAX;
+            assert_eq!(&packet[..2], &[4, 1]);
+            assert_eq!(&packet[2..4], &(start as u16).to_be_bytes());
+            assert_eq!(&packet[4..7], &frame[start]);
+        }
+        assert_eq!(packets[0].len(), 4 + DNRGB_MAX * 3);
+        assert_eq!(packets[2].len(), 4 + 3);
+    }
+
+    fn config(pixels: usize) -> WledConfig {
+        WledConfig {
+            enabled: true,
+            target: "127.0.0.1:9".into(),
+            pixels,
+            ..WledConfig::default()
+        }
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        assert!(WledOutput::from_config(&config(0)).is_err());
+        assert!(WledOutput::from_config(&config(PIXELS_MAX + 1)).is_err());
+        let config = WledConfig {
+            fps: f32::INFINITY,
+            ..config(10)
+        };
+        assert!(WledOutput::from_config(&config).is_err());
+    }
+}
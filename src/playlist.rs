@@ -0,0 +1,318 @@
+use crate::config::{PlaylistConfig, PlaylistEntryConfig};
+use anyhow::{anyhow, Result};
+use rand::seq::SliceRandom;
+use std::time::{Duration, Instant};
+
+/// Beat-counted entries move on after this long per beat even when no beats are
+/// detected, so silence doesn't hold them forever. It is one beat at 30 BPM, the
+/// slowest tempo tracked.
+const SILENT_BEAT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy)]
+enum Dwell {
+    Time(Duration),
+    /// Number of detected beats (bars * 4, assuming 4/4).
+    Beats(usize),
+}
+
+impl Dwell {
+    fn beats(bars: u32) -> Result<Self> {
+        if bars == 0 {
+            return Err(anyhow!("[playlist] bars must be at least 1"));
+        }
+        Ok(Dwell::Beats(bars as usize * 4))
+    }
+
+    /// At least a second, so a tiny value doesn't switch on every frame.
+    fn time(seconds: f32) -> Result<Self> {
+        Duration::try_from_secs_f32(seconds.max(1.0))
+            .map(Dwell::Time)
+            .map_err(|_| anyhow!("[playlist] seconds must be a finite number"))
+    }
+}
+
+struct Entry {
+    visualizer: usize,
+    dwell: Dwell,
+}
+
+/// Rotates through a list of visualizers on a timer or beat count, optionally
+/// holding the switch until the next detected beat so the cut lands on the music.
+pub struct Playlist {
+    entries: Vec<Entry>,
+    order: Vec<usize>,
+    position: usize,
+    shuffle: bool,
+    sync_to_beat: bool,
+    beat_grace: Duration,
+    enabled: bool,
+    started: Instant,
+    beats_at_start: usize,
+    last_total_beats: usize,
+}
+
+impl Playlist {
+    /// Resolves the configured entries against the registered visualizer names.
+    /// An empty entry list cycles through every visualizer in registration order.
+    pub fn from_config(config: &PlaylistConfig, names: &[&str]) -> Result<Self> {
+        let default_dwell = match config.bars {
+            Some(bars) => Dwell::beats(bars)?,
+            None => Dwell::time(config.seconds)?,
+        };
+
+        let entries = if config.entries.is_empty() {
+            (0..names.len())
+                .map(|visualizer| Entry {
+                    visualizer,
+                    dwell: default_dwell,
+                })
+                .collect()
+        } else {
+            config
+                .entries
+                .iter()
+                .map(|entry| {
+                    let (name, seconds, bars) = match entry {
+                        PlaylistEntryConfig::Name(name) => (name, None, None),
+                        PlaylistEntryConfig::Detailed {
+                            name,
+                            seconds,
+                            bars,
+                        } => (name, *seconds, *bars),
+                    };
+                    let visualizer = names
+                        .iter()
+                        .position(|n| n.eq_ignore_ascii_case(name))
+                        .ok_or_else(|| anyhow!("unknown visualizer '{}' in [playlist]", name))?;
+                    let dwell = match (bars, seconds) {
+                        (Some(bars), _) => Dwell::beats(bars)?,
+                        (None, Some(seconds)) => Dwell::time(seconds)?,
+                        (None, None) => default_dwell,
+                    };
+                    Ok(Entry { visualizer, dwell })
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let mut playlist = Self {
+            order: (0..entries.len()).collect(),
+            entries,
+            position: 0,
+            shuffle: config.shuffle,
+            sync_to_beat: config.sync_to_beat,
+            beat_grace: Duration::try_from_secs_f32(config.beat_grace_seconds.max(0.0))
+                .map_err(|_| anyhow!("[playlist] beat_grace_seconds must be a finite number"))?,
+            enabled: config.enabled,
+            started: Instant::now(),
+            beats_at_start: 0,
+            last_total_beats: 0,
+        };
+        if playlist.shuffle {
+            playlist.order.shuffle(&mut rand::rng());
+        }
+        Ok(playlist)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Visualizer the playlist starts on, if it is enabled.
+    pub fn first(&self) -> Option<usize> {
+        if self.enabled {
+            self.order.first().map(|&e| self.entries[e].visualizer)
+        } else {
+            None
+        }
+    }
+
+    pub fn toggle(&mut self, total_beats: usize) {
+        self.enabled = !self.enabled;
+        self.restart(total_beats);
+    }
+
+    /// Starts the dwell period of the current entry over.
+    pub fn restart(&mut self, total_beats: usize) {
+        self.started = Instant::now();
+        self.beats_at_start = total_beats;
+    }
+
+    /// Follows a manual switch to `visualizer`: the playlist carries on from its
+    /// entry (the next one in order, if it appears more than once) with a fresh
+    /// dwell period. A visualizer not on the playlist leaves the position alone.
+    pub fn switched_to(&mut self, visualizer: usize, total_beats: usize) {
+        let len = self.order.len();
+        if let Some(offset) = (0..len).find(|offset| {
+            let position = (self.position + offset) % len;
+            self.entries[self.order[position]].visualizer == visualizer
+        }) {
+            self.position = (self.position + offset) % len;
+        }
+        self.restart(total_beats);
+    }
+
+    /// Advances the playlist clock. Returns the visualizer to switch to, if any.
+    pub fn tick(&mut self, total_beats: usize) -> Option<usize> {
+        let new_beat = total_beats != self.last_total_beats;
+        self.last_total_beats = total_beats;
+
+        if !self.enabled || self.entries.is_empty() {
+            return None;
+        }
+
+        let entry = &self.entries[self.order[self.position]];
+        let switch = match entry.dwell {
+            // Beat-counted entries already switch on a beat.
+            Dwell::Beats(beats) => {
+                total_beats.saturating_sub(self.beats_at_start) >= beats
+                    || self.started.elapsed() >= SILENT_BEAT * beats as u32
+            }
+            Dwell::Time(duration) => {
+                let elapsed = self.started.elapsed();
+                if elapsed < duration {
+                    false
+                } else if self.sync_to_beat {
+                    new_beat || elapsed >= duration + self.beat_grace
+                } else {
+                    true
+                }
+            }
+        };
+
+        if !switch {
+            return None;
+        }
+
+        self.position += 1;
+        if self.position >= self.order.len() {
+            self.position = 0;
+            if self.shuffle && self.order.len() > 1 {
+                let last = *self.order.last().unwrap();
+                self.order.shuffle(&mut rand::rng());
+                // Avoid showing the same visualizer twice in a row across cycles.
+                if self.order[0] == last {
+                    self.order.swap(0, 1);
+                }
+            }
+        }
+        self.restart(total_beats);
+        Some(self.entries[self.order[self.position]].visualizer)
+    }
+
+    /// Time left on the current entry, or beats left for beat-counted entries.
+    pub fn remaining(&self, total_beats: usize) -> String {
+        let entry = self.order.get(self.position).and_then(|&e| self.entries.get(e));
+        match entry.map(|e| e.dwell) {
+            Some(Dwell::Time(duration)) => {
+                format!("{}s", duration.saturating_sub(self.started.elapsed()).as_secs())
+            }
+            Some(Dwell::Beats(beats)) => {
+                let done = total_beats.saturating_sub(self.beats_at_start);
+                format!("{} beats", beats.saturating_sub(done))
+            }
+            None => "-".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 3] = ["Bars", "Radial", "Waves"];
+
+    fn playlist(config: PlaylistConfig) -> Playlist {
+        Playlist::from_config(
+            &PlaylistConfig {
+                enabled: true,
+                ..config
+            },
+            &NAMES,
+        )
+        .unwrap()
+    }
+
+    /// Pretends the current entry started `seconds` ago.
+    fn age(playlist: &mut Playlist, seconds: u64) {
+        playlist.started = Instant::now() - Duration::from_secs(seconds);
+    }
+
+    #[test]
+    fn timed_entries_advance_in_order_and_wrap() {
+        let mut playlist = playlist(PlaylistConfig {
+            seconds: 10.0,
+            sync_to_beat: false,
+            ..PlaylistConfig::default()
+        });
+        assert_eq!(playlist.first(), Some(0));
+        assert_eq!(playlist.tick(0), None);
+
+        for expected in [1, 2, 0] {
+            age(&mut playlist, 11);
+            assert_eq!(playlist.tick(0), Some(expected));
+            assert_eq!(playlist.tick(0), None);
+        }
+    }
+
+    #[test]
+    fn sync_to_beat_waits_for_a_beat_within_the_grace() {
+        let mut playlist = playlist(PlaylistConfig {
+            seconds: 10.0,
+            beat_grace_seconds: 5.0,
+            ..PlaylistConfig::default()
+        });
+        age(&mut playlist, 11);
+        assert_eq!(playlist.tick(0), None);
+        assert_eq!(playlist.tick(1), Some(1));
+
+        age(&mut playlist, 16);
+        assert_eq!(playlist.tick(1), Some(2));
+    }
+
+    #[test]
+    fn beat_counted_entries_advance_on_beats_or_after_silence() {
+        let mut playlist = playlist(PlaylistConfig {
+            bars: Some(1),
+            ..PlaylistConfig::default()
+        });
+        assert_eq!(playlist.tick(3), None);
+        assert_eq!(playlist.tick(4), Some(1));
+
+        // No beats at all: four beats' worth of the slowest tempo.
+        age(&mut playlist, 7);
+        assert_eq!(playlist.tick(4), None);
+        age(&mut playlist, 8);
+        assert_eq!(playlist.tick(4), Some(2));
+    }
+
+    #[test]
+    fn manual_switch_continues_from_the_chosen_entry() {
+        let mut playlist = playlist(PlaylistConfig {
+            seconds: 10.0,
+            sync_to_beat: false,
+            ..PlaylistConfig::default()
+        });
+        playlist.switched_to(2, 0);
+        age(&mut playlist, 11);
+        assert_eq!(playlist.tick(0), Some(0));
+
+        playlist.switched_to(1, 0);
+        assert_eq!(playlist.tick(0), None, "the dwell starts over");
+        age(&mut playlist, 11);
+        assert_eq!(playlist.tick(0), Some(2));
+    }
+
+    #[test]
+    fn rejects_bad_dwell() {
+        let names = NAMES;
+        let bad = |config| Playlist::from_config(&config, &names).is_err();
+        assert!(bad(PlaylistConfig {
+            bars: Some(0),
+            ..PlaylistConfig::default()
+        }));
+        assert!(bad(PlaylistConfig {
+            seconds: f32::INFINITY,
+            ..PlaylistConfig::default()
+        }));
+    }
+}
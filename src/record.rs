@@ -0,0 +1,244 @@
+use anyhow::{Context, Result};
+use crossterm::terminal;
+use serde_json::json;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// An asciicast v2 file: a JSON header line followed by one `[time, code, data]`
+/// event per line.
+struct Cast {
+    file: Box<dyn Write + Send>,
+    started: Instant,
+    size: (u16, u16),
+    /// Current terminal size, checked on every flush.
+    terminal_size: fn() -> io::Result<(u16, u16)>,
+}
+
+impl Cast {
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        let line = json!([self.started.elapsed().as_secs_f64(), code, data]);
+        writeln!(self.file, "{}", line)
+    }
+
+    /// Records a resize if the terminal is no longer the size last recorded.
+    fn resize(&mut self, size: (u16, u16)) -> io::Result<()> {
+        if size != self.size {
+            self.size = size;
+            self.event("r", &format!("{}x{}", size.0, size.1))?;
+        }
+        Ok(())
+    }
+}
+
+/// Terminal output writer that also records everything written to it.
+///
+/// Bytes are collected until the next flush, which the crossterm backend issues once
+/// per drawn frame, so each frame's diff becomes a single timed output event.
+pub struct RecordingWriter<W: Write> {
+    inner: W,
+    cast: Option<Cast>,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> RecordingWriter<W> {
+    /// Passes output through to `inner` without recording.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            cast: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Also records to an asciicast v2 file at `path` for a terminal of `size`.
+    pub fn recording(inner: W, path: &Path, size: (u16, u16)) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create recording {}", path.display()))?;
+        Ok(Self::recording_to(
+            inner,
+            Box::new(BufWriter::new(file)),
+            size,
+            terminal::size,
+        )?)
+    }
+
+    /// Also records to `file` as asciicast v2, for a `width`x`height` terminal whose
+    /// later size `terminal_size` reports.
+    fn recording_to(
+        inner: W,
+        mut file: Box<dyn Write + Send>,
+        (width, height): (u16, u16),
+        terminal_size: fn() -> io::Result<(u16, u16)>,
+    ) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let header = json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": timestamp,
+            "title": "music_visualizer",
+            "env": {
+                "TERM": std::env::var("TERM").unwrap_or_else(|_| "xterm-256color".into()),
+            },
+        });
+        writeln!(file, "{}", header)?;
+        Ok(Self {
+            inner,
+            cast: Some(Cast {
+                file,
+                started: Instant::now(),
+                size: (width, height),
+                terminal_size,
+            }),
+            pending: Vec::new(),
+        })
+    }
+}
+
+impl<W: Write> Write for RecordingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if self.cast.is_some() {
+            self.pending.extend_from_slice(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+        if let Some(cast) = &mut self.cast {
+            // Resizes are noted before the frame redrawn for the new size.
+            if let Ok(size) = (cast.terminal_size)() {
+                cast.resize(size)?;
+            }
+            if !self.pending.is_empty() {
+                // Hold back an incomplete UTF-8 sequence until the rest arrives.
+                let valid = match std::str::from_utf8(&self.pending) {
+                    Ok(_) => self.pending.len(),
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                    Err(_) => self.pending.len(),
+                };
+                let text = String::from_utf8_lossy(&self.pending[..valid]).into_owned();
+                cast.event("o", &text)?;
+                self.pending.drain(..valid);
+            }
+            cast.file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    /// A cast file that can be read back while the writer still owns it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn lines(&self) -> Vec<Value> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    /// Code and data of every event after the header.
+    fn events(cast: &Shared) -> Vec<(String, String)> {
+        cast.lines()[1..]
+            .iter()
+            .map(|event| {
+                assert!(event[0].as_f64().unwrap() >= 0.0);
+                (
+                    event[1].as_str().unwrap().into(),
+                    event[2].as_str().unwrap().into(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn records_header_output_and_resizes() {
+        let cast = Shared::default();
+        // The terminal grows to 100x30 once `GROWN` is set.
+        static GROWN: AtomicBool = AtomicBool::new(false);
+        let mut writer =
+            RecordingWriter::recording_to(Vec::new(), Box::new(cast.clone()), (80, 24), || {
+                Ok(if GROWN.load(Ordering::Relaxed) {
+                    (100, 30)
+                } else {
+                    (80, 24)
+                })
+            })
+            .unwrap();
+
+        let header = &cast.lines()[0];
+        assert_eq!(header["version"], 2);
+        assert_eq!(header["width"], 80);
+        assert_eq!(header["height"], 24);
+
+        writer.write_all(b"\x1b[2Jframe one").unwrap();
+        writer.flush().unwrap();
+        GROWN.store(true, Ordering::Relaxed);
+        writer.write_all(b"frame two").unwrap();
+        writer.flush().unwrap();
+        // Nothing written since the last flush: no empty event.
+        writer.flush().unwrap();
+
+        assert_eq!(writer.inner, b"\x1b[2Jframe oneframe two");
+        assert_eq!(
+            events(&cast),
+            [
+                ("o".into(), "\x1b[2Jframe one".into()),
+                ("r".into(), "100x30".into()),
+                ("o".into(), "frame two".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn holds_back_a_split_utf8_sequence() {
+        let cast = Shared::default();
+        let mut writer =
+            RecordingWriter::recording_to(Vec::new(), Box::new(cast.clone()), (80, 24), || {
+                Ok((80, 24))
+            })
+            .unwrap();
+        let dots = "⣿⣿".as_bytes();
+
+        writer.write_all(&dots[..4]).unwrap();
+        writer.flush().unwrap();
+        writer.write_all(&dots[4..]).unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(
+            events(&cast),
+            [("o".into(), "⣿".into()), ("o".into(), "⣿".into())]
+        );
+    }
+}
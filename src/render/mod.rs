@@ -0,0 +1,208 @@
+//! Offline rendering: steps the analysis and one visualizer through an audio file at
+//! a fixed frame rate and writes the frames as a PNG sequence or an animated GIF.
+
+pub mod font;
+pub mod raster;
+
+use crate::{
+    analysis::{compute_spectrum, hop_size, Analyzer},
+    clock,
+    visualizers::{self, BeatInfo},
+};
+use anyhow::{anyhow, Context, Result};
+use ratatui::{backend::TestBackend, symbols::Marker, Terminal};
+use raster::Image;
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error as DecodeError,
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+/// Seed for the visualizers' randomness, so repeated renders match frame for frame.
+const SEED: u64 = 0x5EED;
+
+pub struct RenderOptions<'a> {
+    pub input: &'a Path,
+    /// A `.gif` file, or a directory that receives `frame-000001.png` and so on.
+    pub output: &'a Path,
+    pub fps: u32,
+    /// Canvas size in cells.
+    pub size: (u16, u16),
+    /// Index of the visualizer to render, as in [`visualizers::all`].
+    pub visualizer: usize,
+}
+
+/// Decodes `path` (WAV, FLAC, MP3 or Ogg Vorbis) to mono samples and their sample rate.
+fn decode(path: &Path) -> Result<(Vec<f32>, u32)> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .with_context(|| format!("unsupported audio file {}", path.display()))?;
+    let mut format = probed.format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| anyhow!("no audio track in {}", path.display()))?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| anyhow!("unknown sample rate in {}", path.display()))?;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut mono = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt packet is skipped rather than ending the render.
+            Err(DecodeError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        samples.copy_interleaved_ref(decoded);
+        mono.extend(
+            samples
+                .samples()
+                .chunks_exact(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+    }
+    Ok((mono, sample_rate))
+}
+
+enum FrameSink {
+    Png(PathBuf),
+    /// The encoder and the frame rate.
+    Gif(gif::Encoder<BufWriter<File>>, u32),
+}
+
+impl FrameSink {
+    fn new(output: &Path, fps: u32, (width, height): (usize, usize)) -> Result<Self> {
+        let is_gif = output
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+        if is_gif && fps > 100 {
+            return Err(anyhow!(
+                "GIF frame delays are in hundredths of a second; use --fps 100 or less"
+            ));
+        }
+        if !is_gif {
+            fs::create_dir_all(output)
+                .with_context(|| format!("failed to create {}", output.display()))?;
+            return Ok(FrameSink::Png(output.to_path_buf()));
+        }
+
+        let (width, height) = (
+            u16::try_from(width).context("frame too wide for GIF")?,
+            u16::try_from(height).context("frame too tall for GIF")?,
+        );
+        let file = File::create(output)
+            .with_context(|| format!("failed to create {}", output.display()))?;
+        let mut encoder = gif::Encoder::new(BufWriter::new(file), width, height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        Ok(FrameSink::Gif(encoder, fps))
+    }
+
+    fn write(&mut self, index: usize, image: &Image) -> Result<()> {
+        match self {
+            FrameSink::Png(dir) => {
+                let path = dir.join(format!("frame-{:06}.png", index + 1));
+                let file = File::create(&path)
+                    .with_context(|| format!("failed to create {}", path.display()))?;
+                let mut encoder =
+                    png::Encoder::new(BufWriter::new(file), image.width as u32, image.height as u32);
+                encoder.set_color(png::ColorType::Rgb);
+                encoder.set_depth(png::BitDepth::Eight);
+                let mut writer = encoder.write_header()?;
+                writer.write_image_data(&image.rgb)?;
+            }
+            FrameSink::Gif(encoder, fps) => {
+                let mut frame = gif::Frame::from_rgb_speed(
+                    image.width as u16,
+                    image.height as u16,
+                    &image.rgb,
+                    10,
+                );
+                // GIF delays are in hundredths of a second. Each frame ends at its exact
+                // time rounded down, so rounding errors don't add up over the video.
+                let end = |index: usize| index as u64 * 100 / *fps as u64;
+                frame.delay = (end(index + 1) - end(index)) as u16;
+                encoder.write_frame(&frame)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders the chosen visualizer for the whole of `options.input`. The analysis steps
+/// once per hop as it does live, and each frame draws the latest step with the clock
+/// exactly one frame further on, so the output doesn't depend on how fast the machine is.
+pub fn render(options: &RenderOptions, marker: Marker) -> Result<()> {
+    let (samples, sample_rate) = decode(options.input)?;
+    let fps = options.fps.max(1);
+    let frames = (samples.len() as u64 * fps as u64).div_ceil(sample_rate as u64) as usize;
+
+    clock::seed(SEED);
+    clock::set_stepped(Duration::ZERO);
+    // Built only now, so whatever it randomizes or timestamps on creation comes from
+    // the seeded generator and the stepped clock.
+    let visualizer = visualizers::all().swap_remove(options.visualizer);
+    let mut analyzer = Analyzer::new();
+    let (width, height) = options.size;
+    let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+    let mut sink = FrameSink::new(
+        options.output,
+        fps,
+        (width as usize * raster::CELL_WIDTH, height as usize * raster::CELL_HEIGHT),
+    )?;
+
+    let hop = hop_size(sample_rate);
+    let mut next_hop = hop;
+    let mut spectrum = None;
+    let mut beat_info = BeatInfo {
+        is_beat: false,
+        bpm: 0.0,
+        total_beats: 0,
+    };
+    for index in 0..frames {
+        clock::set_stepped(Duration::from_secs_f64(index as f64 / fps as f64));
+        let end = ((index as u64 + 1) * sample_rate as u64 / fps as u64) as usize;
+        // A beat in any of the frame's steps shows on the frame.
+        let mut is_beat = false;
+        while next_hop <= end.min(samples.len()) {
+            spectrum = compute_spectrum(&samples[..next_hop], sample_rate);
+            let time = Duration::from_secs_f64(next_hop as f64 / sample_rate as f64);
+            beat_info = analyzer.update(spectrum.as_ref(), time);
+            is_beat |= beat_info.is_beat;
+            next_hop += hop;
+        }
+        beat_info.is_beat = is_beat;
+
+        terminal.clear()?;
+        if let Some(spectrum) = &spectrum {
+            terminal.draw(|f| visualizer.draw(f, f.area(), spectrum, &beat_info, marker))?;
+        }
+        sink.write(index, &raster::rasterize(terminal.backend().buffer()))?;
+    }
+    Ok(())
+}
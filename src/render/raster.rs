@@ -0,0 +1,222 @@
+use super::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::export::{braille_bits, cell_rgb, Rgb, DEFAULT_BG};
+use ratatui::buffer::Buffer;
+
+/// Pixel size of one terminal cell.
+pub const CELL_WIDTH: usize = 8;
+pub const CELL_HEIGHT: usize = 16;
+
+/// Packed 8-bit RGB image.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub rgb: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize, color: Rgb) -> Self {
+        let rgb = [color.0, color.1, color.2].repeat(width * height);
+        Self { width, height, rgb }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, w: usize, h: usize, color: Rgb) {
+        for py in y..(y + h).min(self.height) {
+            for px in x..(x + w).min(self.width) {
+                let i = (py * self.width + px) * 3;
+                self.rgb[i..i + 3].copy_from_slice(&[color.0, color.1, color.2]);
+            }
+        }
+    }
+}
+
+/// Draws every cell of `buf` as a `CELL_WIDTH`x`CELL_HEIGHT` block of pixels.
+pub fn rasterize(buf: &Buffer) -> Image {
+    let area = buf.area;
+    let mut image = Image::new(
+        area.width as usize * CELL_WIDTH,
+        area.height as usize * CELL_HEIGHT,
+        DEFAULT_BG,
+    );
+
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            let cell = &buf[(x, y)];
+            let (fg, bg) = cell_rgb(cell);
+            let px = (x - area.x) as usize * CELL_WIDTH;
+            let py = (y - area.y) as usize * CELL_HEIGHT;
+            let bg = bg.unwrap_or(DEFAULT_BG);
+            image.fill(px, py, CELL_WIDTH, CELL_HEIGHT, bg);
+            draw_symbol(&mut image, px, py, cell.symbol(), fg, bg);
+        }
+    }
+    image
+}
+
+fn draw_symbol(image: &mut Image, x: usize, y: usize, symbol: &str, fg: Rgb, bg: Rgb) {
+    let Some(c) = symbol.chars().next() else {
+        return;
+    };
+    if c == ' ' {
+        return;
+    }
+
+    if let Some(bits) = braille_bits(symbol) {
+        // Bits 0-2 and 6 are the left column top to bottom, 3-5 and 7 the right.
+        const DOTS: [(usize, usize); 8] = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2), (0, 3), (1, 3)];
+        let (w, h) = (CELL_WIDTH / 2, CELL_HEIGHT / 4);
+        for (bit, (col, row)) in DOTS.iter().enumerate() {
+            if bits & (1 << bit) != 0 {
+                image.fill(x + col * w, y + row * h, w - 1, h - 1, fg);
+            }
+        }
+        return;
+    }
+
+    if let Some(mask) = quadrants(c) {
+        let (w, h) = (CELL_WIDTH / 2, CELL_HEIGHT / 2);
+        for (bit, (col, row)) in [(0, 0), (1, 0), (0, 1), (1, 1)].into_iter().enumerate() {
+            if mask & (1 << bit) != 0 {
+                image.fill(x + col * w, y + row * h, w, h, fg);
+            }
+        }
+        return;
+    }
+
+    if let Some(bits) = sextant_bits(c) {
+        let rows = [0, CELL_HEIGHT / 3, CELL_HEIGHT * 2 / 3, CELL_HEIGHT];
+        let w = CELL_WIDTH / 2;
+        for bit in 0..6 {
+            if bits & (1 << bit) != 0 {
+                let (col, row) = (bit % 2, bit / 2);
+                image.fill(x + col * w, y + rows[row], w, rows[row + 1] - rows[row], fg);
+            }
+        }
+        return;
+    }
+
+    match c as u32 {
+        // Lower one-eighth to seven-eighths blocks.
+        0x2581..=0x2587 => {
+            let h = CELL_HEIGHT * (c as usize - 0x2580) / 8;
+            image.fill(x, y + CELL_HEIGHT - h, CELL_WIDTH, h, fg);
+            return;
+        }
+        // Left seven-eighths down to one-eighth blocks.
+        0x2589..=0x258F => {
+            let w = CELL_WIDTH * (0x2590 - c as usize) / 8;
+            image.fill(x, y, w.max(1), CELL_HEIGHT, fg);
+            return;
+        }
+        // Light, medium and dark shade.
+        0x2591..=0x2593 => {
+            let alpha = (c as u32 - 0x2590) as f32 / 4.0;
+            image.fill(x, y, CELL_WIDTH, CELL_HEIGHT, mix(bg, fg, alpha));
+            return;
+        }
+        _ => {}
+    }
+
+    if let Some((up, down, left, right)) = box_lines(c) {
+        let (cx, cy) = (x + CELL_WIDTH / 2 - 1, y + CELL_HEIGHT / 2 - 1);
+        if up {
+            image.fill(cx, y, 2, CELL_HEIGHT / 2, fg);
+        }
+        if down {
+            image.fill(cx, cy, 2, CELL_HEIGHT / 2 + 1, fg);
+        }
+        if left {
+            image.fill(x, cy, CELL_WIDTH / 2, 2, fg);
+        }
+        if right {
+            image.fill(cx, cy, CELL_WIDTH / 2 + 1, 2, fg);
+        }
+        return;
+    }
+
+    if let Some(columns) = font::glyph(c) {
+        // Scaled 1x2 so the 5x7 glyph fills most of the tall cell.
+        let (ox, oy) = (x + (CELL_WIDTH - GLYPH_WIDTH) / 2, y + (CELL_HEIGHT - GLYPH_HEIGHT * 2) / 2);
+        for (col, bits) in columns.iter().enumerate() {
+            for row in 0..GLYPH_HEIGHT {
+                if bits & (1 << row) != 0 {
+                    image.fill(ox + col, oy + row * 2, 1, 2, fg);
+                }
+            }
+        }
+        return;
+    }
+
+    // Anything else (note symbols, octants, bullets) becomes a centered dot.
+    let size = if c == '·' { 2 } else { 4 };
+    image.fill(
+        x + (CELL_WIDTH - size) / 2,
+        y + (CELL_HEIGHT - size) / 2,
+        size,
+        size,
+        fg,
+    );
+}
+
+fn mix(a: Rgb, b: Rgb, t: f32) -> Rgb {
+    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    (lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+}
+
+/// Quadrant and half/full block characters as a mask of top-left, top-right,
+/// bottom-left and bottom-right (bits 0 to 3).
+fn quadrants(c: char) -> Option<u8> {
+    Some(match c {
+        '▘' => 0b0001,
+        '▝' => 0b0010,
+        '▖' => 0b0100,
+        '▗' => 0b1000,
+        '▀' => 0b0011,
+        '▄' => 0b1100,
+        '▌' => 0b0101,
+        '▐' => 0b1010,
+        '▚' => 0b1001,
+        '▞' => 0b0110,
+        '▛' => 0b0111,
+        '▜' => 0b1011,
+        '▙' => 0b1101,
+        '▟' => 0b1110,
+        '█' => 0b1111,
+        _ => return None,
+    })
+}
+
+/// Dot pattern of a sextant character (U+1FB00..U+1FB3B), bit 0 top-left to bit 5
+/// bottom-right. The block range leaves out the patterns that already exist as
+/// half blocks.
+fn sextant_bits(c: char) -> Option<u8> {
+    let code = c as u32;
+    if !(0x1FB00..=0x1FB3B).contains(&code) {
+        return None;
+    }
+    let mut bits = (code - 0x1FB00) as u8 + 1;
+    if bits >= 21 {
+        bits += 1;
+    }
+    if bits >= 42 {
+        bits += 1;
+    }
+    Some(bits)
+}
+
+/// Which directions a box-drawing character's lines go: (up, down, left, right).
+fn box_lines(c: char) -> Option<(bool, bool, bool, bool)> {
+    Some(match c {
+        '─' | '━' | '═' => (false, false, true, true),
+        '│' | '┃' | '║' => (true, true, false, false),
+        '┌' | '┏' | '╔' | '╭' => (false, true, false, true),
+        '┐' | '┓' | '╗' | '╮' => (false, true, true, false),
+        '└' | '┗' | '╚' | '╰' => (true, false, false, true),
+        '┘' | '┛' | '╝' | '╯' => (true, false, true, false),
+        '├' | '┣' | '╠' => (true, true, false, true),
+        '┤' | '┫' | '╣' => (true, true, true, false),
+        '┬' | '┳' | '╦' => (false, true, true, true),
+        '┴' | '┻' | '╩' => (true, false, true, true),
+        '┼' | '╋' | '╬' => (true, true, true, true),
+        _ => return None,
+    })
+}
@@ -0,0 +1,57 @@
+use super::popup_area;
+use crate::keymap::{Action, Keymap};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+pub fn draw_help(f: &mut Frame, area: Rect, keymap: &Keymap) {
+    let mut rows: Vec<(String, String)> = Vec::new();
+    let mut select_keys: Vec<String> = Vec::new();
+    for action in Action::all() {
+        let labels = keymap.labels(action);
+        if let Action::Select(_) = action {
+            // The nine jump actions are folded into a single row.
+            select_keys.extend(labels.into_iter().take(1));
+            continue;
+        }
+        if !labels.is_empty() {
+            rows.push((labels.join(" / "), action.description()));
+        }
+    }
+    if !select_keys.is_empty() {
+        rows.push((select_keys.join(" "), "Jump to visualizer N".to_string()));
+    }
+
+    let key_width = rows.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0);
+    let lines: Vec<Line> = rows
+        .into_iter()
+        .map(|(key, desc)| {
+            Line::from(vec![
+                Span::styled(
+                    format!(" {:>width$} ", key, width = key_width),
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(" {}", desc)),
+            ])
+        })
+        .collect();
+
+    let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 4;
+    let height = lines.len() as u16 + 2;
+    let popup = popup_area(area, width, height);
+
+    let help = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Help ")
+            .title_bottom(" any key to close ")
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+
+    f.render_widget(Clear, popup);
+    f.render_widget(help, popup);
+}
@@ -0,0 +1,97 @@
+use crate::visualizers::FrequencyAxis;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use spectrum_analyzer::FrequencySpectrum;
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Nearest equal-tempered note (A4 = 440 Hz) and how far off it is, e.g. `A4 +3¢`.
+fn note_name(freq: f32) -> String {
+    let midi = 69.0 + 12.0 * (freq / 440.0).log2();
+    let nearest = midi.round();
+    let cents = ((midi - nearest) * 100.0).round() as i32;
+    let n = nearest as i32;
+    format!(
+        "{}{} {:+}¢",
+        NOTE_NAMES[n.rem_euclid(12) as usize],
+        n.div_euclid(12) - 1,
+        cents
+    )
+}
+
+/// Movable column cursor over a frozen spectrum view that reads out frequency,
+/// magnitude and nearest note.
+pub struct Inspector {
+    column: u16,
+}
+
+impl Inspector {
+    pub fn new() -> Self {
+        Self { column: 0 }
+    }
+
+    pub fn move_left(&mut self) {
+        self.column = self.column.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.column = self.column.saturating_add(1);
+    }
+
+    /// Draws the cursor into `area` (a visualizer's full area, border included).
+    pub fn draw(
+        &mut self,
+        f: &mut Frame,
+        area: Rect,
+        axis: FrequencyAxis,
+        spectrum: &FrequencySpectrum,
+    ) {
+        let inner = Block::default().borders(Borders::ALL).inner(area);
+        if inner.width == 0 || inner.height == 0 {
+            return;
+        }
+        self.column = self.column.min(inner.width - 1);
+        let x = inner.x + self.column;
+
+        let freq = axis.frequency_at((self.column as f32 + 0.5) / inner.width as f32);
+        let (bin_freq, magnitude) = spectrum.freq_val_closest(freq);
+        let db = 20.0 * magnitude.val().max(1e-9).log10();
+
+        let buf = f.buffer_mut();
+        for y in inner.top()..inner.bottom() {
+            let cell = &mut buf[(x, y)];
+            if cell.symbol() == " " {
+                cell.set_symbol("│");
+            }
+            cell.set_fg(Color::Yellow);
+        }
+
+        let readout = format!(
+            " {:.1} Hz (bin {:.0} Hz) | {:.1} dB | {} ",
+            freq,
+            bin_freq.val(),
+            db,
+            note_name(freq)
+        );
+        let width = (readout.chars().count() as u16 + 2).min(inner.width);
+        // Keep the readout next to the cursor but inside the pane.
+        let left = x
+            .saturating_sub(width / 2)
+            .clamp(inner.x, inner.right().saturating_sub(width));
+        let popup = Rect::new(left, inner.y, width, 3.min(inner.height));
+
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(readout)
+                .style(Style::default().fg(Color::Yellow))
+                .block(Block::default().borders(Borders::ALL).title(" Inspect ")),
+            popup,
+        );
+    }
+}
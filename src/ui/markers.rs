@@ -0,0 +1,88 @@
+use crate::config::MarkersConfig;
+use anyhow::{anyhow, Result};
+use ratatui::symbols::Marker;
+use std::collections::HashMap;
+
+/// Markers in the order they are cycled through at runtime.
+const MARKERS: [Marker; 8] = [
+    Marker::Braille,
+    Marker::HalfBlock,
+    Marker::Quadrant,
+    Marker::Sextant,
+    Marker::Octant,
+    Marker::Block,
+    Marker::Dot,
+    Marker::Bar,
+];
+
+/// Accepts names like `braille`, `HalfBlock` or `half-block`.
+fn parse_marker(name: &str) -> Result<Marker> {
+    let wanted = name.replace(['-', '_'], "");
+    MARKERS
+        .iter()
+        .copied()
+        .find(|m| m.to_string().eq_ignore_ascii_case(&wanted))
+        .ok_or_else(|| anyhow!("unknown canvas marker '{}'", name))
+}
+
+fn next_marker(marker: Marker) -> Marker {
+    let pos = MARKERS.iter().position(|m| *m == marker).unwrap_or(0);
+    MARKERS[(pos + 1) % MARKERS.len()]
+}
+
+/// Canvas marker used for each visualizer: a global default plus per-visualizer overrides.
+pub struct MarkerSettings {
+    global: Marker,
+    overrides: HashMap<usize, Marker>,
+}
+
+impl MarkerSettings {
+    pub fn from_config(config: &MarkersConfig, names: &[&str]) -> Result<Self> {
+        let mut overrides = HashMap::new();
+        for (name, marker) in &config.visualizers {
+            let idx = names
+                .iter()
+                .position(|n| n.eq_ignore_ascii_case(name))
+                .ok_or_else(|| anyhow!("unknown visualizer '{}' in [markers.visualizers]", name))?;
+            overrides.insert(idx, parse_marker(marker)?);
+        }
+        Ok(Self {
+            global: parse_marker(&config.default)?,
+            overrides,
+        })
+    }
+
+    pub fn for_visualizer(&self, idx: usize) -> Marker {
+        self.overrides.get(&idx).copied().unwrap_or(self.global)
+    }
+
+    pub fn cycle_global(&mut self) {
+        self.global = next_marker(self.global);
+    }
+
+    /// Steps the override for one visualizer through every marker, then back to
+    /// following the global setting.
+    pub fn cycle_override(&mut self, idx: usize) {
+        match self.overrides.get(&idx).copied() {
+            None => {
+                self.overrides.insert(idx, next_marker(self.global));
+            }
+            Some(marker) if next_marker(marker) == self.global => {
+                self.overrides.remove(&idx);
+            }
+            Some(marker) => {
+                self.overrides.insert(idx, next_marker(marker));
+            }
+        }
+    }
+
+    /// Short description for the info panel, e.g. `Braille` or `HalfBlock*` when overridden.
+    pub fn label(&self, idx: usize) -> String {
+        let marker = self.for_visualizer(idx);
+        if self.overrides.contains_key(&idx) {
+            format!("{}*", marker)
+        } else {
+            marker.to_string()
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use crate::visualizers::BeatInfo;
+use markers::MarkerSettings;
+use ratatui::layout::{Constraint, Rect};
+use spectrum_analyzer::FrequencySpectrum;
+
+pub mod help;
+pub mod inspector;
+pub mod markers;
+pub mod panes;
+pub mod picker;
+pub mod theme;
+pub mod transition;
+
+/// Returns a rect of the given size centered inside `area`, shrunk to fit if needed.
+pub fn popup_area(area: Rect, width: u16, height: u16) -> Rect {
+    area.centered(
+        Constraint::Length(width.min(area.width)),
+        Constraint::Length(height.min(area.height)),
+    )
+}
+
+/// Everything a pane needs to draw one frame of its visualizers.
+pub struct DrawContext<'a> {
+    pub spectrum: &'a FrequencySpectrum,
+    pub beat_info: &'a BeatInfo,
+    pub markers: &'a MarkerSettings,
+}
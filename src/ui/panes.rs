@@ -0,0 +1,245 @@
+use super::{
+    transition::{Transition, TransitionKind},
+    DrawContext,
+};
+use crate::visualizers::{self, FrequencyAxis, Visualizer};
+use anyhow::Result;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    Frame,
+};
+use serde::Deserialize;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum LayoutPreset {
+    /// One full-size pane.
+    #[serde(rename = "single")]
+    Single,
+    /// Two panes side by side.
+    #[serde(rename = "2-up")]
+    SideBySide,
+    /// Two panes stacked vertically.
+    #[serde(rename = "2-stack")]
+    Stacked,
+    /// Four panes in a 2x2 grid.
+    #[serde(rename = "4-up")]
+    Grid,
+    /// A large pane on the left with two small ones stacked on the right.
+    #[serde(rename = "main-sidebar")]
+    MainSidebar,
+    /// A wide pane on top with two panes side by side below it.
+    #[serde(rename = "top-split")]
+    TopSplit,
+}
+
+impl LayoutPreset {
+    pub fn pane_count(&self) -> usize {
+        match self {
+            LayoutPreset::Single => 1,
+            LayoutPreset::SideBySide | LayoutPreset::Stacked => 2,
+            LayoutPreset::MainSidebar | LayoutPreset::TopSplit => 3,
+            LayoutPreset::Grid => 4,
+        }
+    }
+
+    fn split(&self, area: Rect) -> Vec<Rect> {
+        let halves = |direction| {
+            Layout::default()
+                .direction(direction)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(area)
+                .to_vec()
+        };
+
+        match self {
+            LayoutPreset::Single => vec![area],
+            LayoutPreset::SideBySide => halves(Direction::Horizontal),
+            LayoutPreset::Stacked => halves(Direction::Vertical),
+            LayoutPreset::Grid => halves(Direction::Vertical)
+                .into_iter()
+                .flat_map(|row| {
+                    Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(row)
+                        .to_vec()
+                })
+                .collect(),
+            LayoutPreset::MainSidebar | LayoutPreset::TopSplit => {
+                let (outer, inner) = if *self == LayoutPreset::MainSidebar {
+                    (Direction::Horizontal, Direction::Vertical)
+                } else {
+                    (Direction::Vertical, Direction::Horizontal)
+                };
+                let main = Layout::default()
+                    .direction(outer)
+                    .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+                    .split(area);
+                let side = Layout::default()
+                    .direction(inner)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(main[1]);
+                vec![main[0], side[0], side[1]]
+            }
+        }
+    }
+}
+
+/// One region of the screen with its own set of visualizer instances, so two panes
+/// showing the same style don't share particles or rotation.
+pub struct Pane {
+    visualizers: Vec<Box<dyn Visualizer>>,
+    current: usize,
+    shown: usize,
+    transition: Transition,
+}
+
+impl Pane {
+    pub fn new(current: usize, kind: TransitionKind, duration: Duration) -> Result<Self> {
+        Ok(Self {
+            visualizers: visualizers::all(),
+            current,
+            shown: current,
+            transition: Transition::new(kind, duration)?,
+        })
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn frequency_axis(&self) -> Option<FrequencyAxis> {
+        self.visualizers[self.current].frequency_axis()
+    }
+
+    /// Sets a live parameter of the visualizer on show; false if it has no such one.
+    pub fn set_param(&self, name: &str, value: f32) -> bool {
+        self.visualizers[self.current].set_param(name, value)
+    }
+
+    pub fn select(&mut self, idx: usize) {
+        if idx < self.visualizers.len() {
+            self.current = idx;
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.visualizers.len();
+    }
+
+    pub fn prev(&mut self) {
+        self.current = (self.current + self.visualizers.len() - 1) % self.visualizers.len();
+    }
+
+    fn draw(&mut self, f: &mut Frame, area: Rect, ctx: &DrawContext) -> Result<()> {
+        if self.current != self.shown {
+            self.transition.start(self.shown);
+            self.shown = self.current;
+        }
+        self.transition
+            .draw(f, area, &self.visualizers, self.current, ctx)
+    }
+}
+
+pub struct Panes {
+    preset: LayoutPreset,
+    panes: Vec<Pane>,
+    focused: usize,
+    frozen: bool,
+    /// Last frame drawn while frozen and the visualizer each pane showed in it, replayed
+    /// instead of advancing the visualizers.
+    snapshot: Option<(Buffer, Vec<usize>)>,
+}
+
+impl Panes {
+    /// Creates one pane per slot of `preset`, starting on the visualizers in `initial`
+    /// (falling back to consecutive visualizers for panes not listed).
+    pub fn new(
+        preset: LayoutPreset,
+        initial: &[usize],
+        kind: TransitionKind,
+        duration: Duration,
+    ) -> Result<Self> {
+        let count = visualizers::names().len();
+        let panes = (0..preset.pane_count())
+            .map(|i| Pane::new(initial.get(i).copied().unwrap_or(i % count), kind, duration))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            preset,
+            panes,
+            focused: 0,
+            frozen: false,
+            snapshot: None,
+        })
+    }
+
+    pub fn focused(&self) -> &Pane {
+        &self.panes[self.focused]
+    }
+
+    pub fn focused_mut(&mut self) -> &mut Pane {
+        &mut self.panes[self.focused]
+    }
+
+    pub fn focus_next(&mut self) {
+        self.focused = (self.focused + 1) % self.panes.len();
+    }
+
+    pub fn focus_prev(&mut self) {
+        self.focused = (self.focused + self.panes.len() - 1) % self.panes.len();
+    }
+
+    /// Area of the focused pane when the panes are laid out in `area`.
+    pub fn focused_area(&self, area: Rect) -> Rect {
+        self.preset.split(area)[self.focused]
+    }
+
+    /// While frozen, visualizer state stops advancing and the same picture is redrawn.
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+        self.snapshot = None;
+    }
+
+    pub fn draw(&mut self, f: &mut Frame, area: Rect, ctx: &DrawContext) -> Result<()> {
+        let areas = self.preset.split(area);
+        let shown: Vec<usize> = self.panes.iter().map(|p| p.current).collect();
+
+        match &self.snapshot {
+            Some((snapshot, was_shown)) if snapshot.area == area && *was_shown == shown => {
+                for pos in area.positions() {
+                    f.buffer_mut()[pos] = snapshot[pos].clone();
+                }
+            }
+            _ => {
+                for (pane, pane_area) in self.panes.iter_mut().zip(areas.iter()) {
+                    pane.draw(f, *pane_area, ctx)?;
+                }
+                if self.frozen {
+                    let mut snapshot = Buffer::empty(area);
+                    for pos in area.positions() {
+                        snapshot[pos] = f.buffer_mut()[pos].clone();
+                    }
+                    self.snapshot = Some((snapshot, shown));
+                }
+            }
+        }
+
+        // Tag the focused pane's top-right corner when there is more than one.
+        if self.panes.len() > 1 {
+            let focused = areas[self.focused];
+            if focused.width > 4 {
+                let x = focused.right() - 4;
+                f.buffer_mut().set_string(
+                    x,
+                    focused.y,
+                    "[●]",
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                );
+            }
+        }
+        Ok(())
+    }
+}
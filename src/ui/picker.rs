@@ -0,0 +1,180 @@
+use super::popup_area;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+pub enum PickerOutcome {
+    /// The key was consumed and the picker stays open.
+    Pending,
+    /// The user picked the visualizer at this index of the full list.
+    Selected(usize),
+    /// The picker was dismissed without a selection.
+    Closed,
+}
+
+/// Searchable popup listing every registered visualizer by name.
+pub struct VisualizerPicker {
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl VisualizerPicker {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self, current: usize) {
+        self.open = true;
+        self.query.clear();
+        self.selected = current;
+    }
+
+    /// Indices into `names` that match the current query (case-insensitive substring).
+    fn filtered(&self, names: &[&str]) -> Vec<usize> {
+        let query = self.query.to_lowercase();
+        names
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| name.to_lowercase().contains(&query))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, names: &[&str]) -> PickerOutcome {
+        let matches = self.filtered(names);
+
+        let outcome = match key.code {
+            KeyCode::Esc => PickerOutcome::Closed,
+            KeyCode::Enter => match matches.get(self.selected) {
+                Some(&idx) => PickerOutcome::Selected(idx),
+                None => PickerOutcome::Pending,
+            },
+            KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                PickerOutcome::Pending
+            }
+            KeyCode::Down => {
+                if self.selected + 1 < matches.len() {
+                    self.selected += 1;
+                }
+                PickerOutcome::Pending
+            }
+            // Digits pick an entry only until a search is started, so names with
+            // digits in them can still be typed.
+            KeyCode::Char(c @ '1'..='9') if self.query.is_empty() => {
+                let n = c as usize - '1' as usize;
+                match matches.get(n) {
+                    Some(&idx) => PickerOutcome::Selected(idx),
+                    None => PickerOutcome::Pending,
+                }
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+                PickerOutcome::Pending
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.selected = 0;
+                PickerOutcome::Pending
+            }
+            _ => PickerOutcome::Pending,
+        };
+
+        if !matches!(outcome, PickerOutcome::Pending) {
+            self.open = false;
+        }
+        outcome
+    }
+
+    pub fn draw(&self, f: &mut Frame, area: Rect, names: &[&str], current: usize) {
+        let matches = self.filtered(names);
+        let width = names.iter().map(|n| n.len()).max().unwrap_or(0) as u16 + 12;
+        let height = names.len() as u16 + 5;
+        let popup = popup_area(area, width.max(30), height);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Visualizers ")
+            .title_bottom(" ↑↓/1-9 select, Enter pick, Esc close ")
+            .border_style(Style::default().fg(Color::Cyan));
+        let inner = block.inner(popup);
+
+        f.render_widget(Clear, popup);
+        f.render_widget(block, popup);
+
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(0)])
+            .split(inner);
+
+        let search = Paragraph::new(format!(" / {}_", self.query))
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(search, sections[0]);
+
+        let items: Vec<ListItem> = matches
+            .iter()
+            .enumerate()
+            .map(|(pos, &idx)| {
+                let number = if pos < 9 {
+                    format!("{}", pos + 1)
+                } else {
+                    " ".to_string()
+                };
+                let marker = if idx == current { "*" } else { " " };
+                ListItem::new(format!(" {} {} {}", number, marker, names[idx]))
+            })
+            .collect();
+
+        let list = List::new(items).highlight_style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        );
+        let mut state = ListState::default().with_selected(if matches.is_empty() {
+            None
+        } else {
+            Some(self.selected.min(matches.len() - 1))
+        });
+        f.render_stateful_widget(list, sections[1], &mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn press(picker: &mut VisualizerPicker, c: char, names: &[&str]) -> PickerOutcome {
+        picker.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), names)
+    }
+
+    #[test]
+    fn digits_select_until_a_query_is_typed() {
+        let names = ["Bars", "Waves 2", "Waves 3"];
+        let mut picker = VisualizerPicker::new();
+
+        picker.open(0);
+        assert!(matches!(press(&mut picker, '2', &names), PickerOutcome::Selected(1)));
+
+        picker.open(0);
+        press(&mut picker, 's', &names);
+        press(&mut picker, ' ', &names);
+        assert!(matches!(press(&mut picker, '3', &names), PickerOutcome::Pending));
+        assert_eq!(picker.filtered(&names), vec![2]);
+    }
+}
@@ -0,0 +1,113 @@
+//! Palettes the visualization can be recolored with while running.
+//!
+//! Visualizers draw with the terminal's named colors; a theme maps each of those by
+//! brightness onto its own four-step palette after the frame is drawn, so every
+//! visualizer follows it without knowing about themes.
+
+use ratatui::{buffer::Buffer, layout::Rect, style::Color};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// Each visualizer's own colors.
+    #[default]
+    Default,
+    Mono,
+    Amber,
+    Ocean,
+    Neon,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 5] = [
+        Theme::Default,
+        Theme::Mono,
+        Theme::Amber,
+        Theme::Ocean,
+        Theme::Neon,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Default => "default",
+            Theme::Mono => "mono",
+            Theme::Amber => "amber",
+            Theme::Ocean => "ocean",
+            Theme::Neon => "neon",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|theme| theme.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Dark to bright, or `None` to leave colors alone.
+    fn palette(self) -> Option<[Color; 4]> {
+        match self {
+            Theme::Default => None,
+            Theme::Mono => Some([
+                Color::Rgb(70, 70, 70),
+                Color::Rgb(130, 130, 130),
+                Color::Rgb(190, 190, 190),
+                Color::Rgb(250, 250, 250),
+            ]),
+            Theme::Amber => Some([
+                Color::Rgb(90, 45, 0),
+                Color::Rgb(170, 95, 0),
+                Color::Rgb(240, 150, 10),
+                Color::Rgb(255, 215, 120),
+            ]),
+            Theme::Ocean => Some([
+                Color::Rgb(10, 40, 90),
+                Color::Rgb(20, 90, 160),
+                Color::Rgb(40, 170, 210),
+                Color::Rgb(170, 240, 255),
+            ]),
+            Theme::Neon => Some([
+                Color::Rgb(90, 0, 140),
+                Color::Rgb(230, 0, 170),
+                Color::Rgb(0, 230, 255),
+                Color::Rgb(220, 255, 60),
+            ]),
+        }
+    }
+
+    /// The palette entry standing in for `color`. Black and the terminal default are
+    /// kept so backgrounds stay as they are.
+    fn map(palette: &[Color; 4], color: Color) -> Color {
+        let level = match color {
+            Color::Reset | Color::Black => return color,
+            Color::DarkGray => 0,
+            Color::Red | Color::Green | Color::Blue | Color::Magenta => 1,
+            Color::Gray
+            | Color::Cyan
+            | Color::Yellow
+            | Color::LightRed
+            | Color::LightGreen
+            | Color::LightBlue
+            | Color::LightMagenta
+            | Color::LightCyan
+            | Color::LightYellow => 2,
+            Color::White => 3,
+            Color::Rgb(r, g, b) => {
+                let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+                (luma / 64.0) as usize
+            }
+            Color::Indexed(_) => 2,
+        };
+        palette[level.min(3)]
+    }
+
+    /// Recolors the foreground and background of every cell in `area`.
+    pub fn apply(self, buf: &mut Buffer, area: Rect) {
+        let Some(palette) = self.palette() else {
+            return;
+        };
+        for pos in area.intersection(buf.area).positions() {
+            let cell = &mut buf[pos];
+            cell.fg = Self::map(&palette, cell.fg);
+            cell.bg = Self::map(&palette, cell.bg);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use super::DrawContext;
+use crate::visualizers::{BeatInfo, Visualizer};
+use anyhow::Result;
+use ratatui::{
+    backend::TestBackend,
+    buffer::Buffer,
+    layout::Rect,
+    symbols::Marker,
+    Frame, Terminal,
+};
+use serde::Deserialize;
+use spectrum_analyzer::FrequencySpectrum;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransitionKind {
+    /// Instant switch, no transition.
+    Cut,
+    /// Incoming visualizer is revealed left to right.
+    Wipe,
+    /// Cells flip to the incoming visualizer in a scattered order.
+    Dissolve,
+    /// Incoming visualizer pushes the outgoing one off to the left.
+    Slide,
+}
+
+/// Blends the outgoing and incoming visualizer while switching.
+///
+/// Both are drawn into off-screen buffers through a [`TestBackend`] terminal, so any
+/// [`Visualizer`] works without knowing it is part of a transition.
+pub struct Transition {
+    kind: TransitionKind,
+    duration: Duration,
+    from: Option<(usize, Instant)>,
+    outgoing: Terminal<TestBackend>,
+    incoming: Terminal<TestBackend>,
+}
+
+impl Transition {
+    pub fn new(kind: TransitionKind, duration: Duration) -> Result<Self> {
+        Ok(Self {
+            kind,
+            duration,
+            from: None,
+            outgoing: Terminal::new(TestBackend::new(1, 1))?,
+            incoming: Terminal::new(TestBackend::new(1, 1))?,
+        })
+    }
+
+    /// Starts a transition away from visualizer `from`.
+    pub fn start(&mut self, from: usize) {
+        if self.kind != TransitionKind::Cut && !self.duration.is_zero() {
+            self.from = Some((from, Instant::now()));
+        }
+    }
+
+    /// Outgoing visualizer index and eased progress (0..1), while a transition is running.
+    fn active(&mut self) -> Option<(usize, f32)> {
+        let (from, started) = self.from?;
+        let t = started.elapsed().as_secs_f32() / self.duration.as_secs_f32();
+        if t >= 1.0 {
+            self.from = None;
+            return None;
+        }
+        // Smoothstep so the motion eases in and out.
+        Some((from, t * t * (3.0 - 2.0 * t)))
+    }
+
+    /// Draws `to` into `area`, composited with the outgoing visualizer if a
+    /// transition is in progress.
+    pub fn draw(
+        &mut self,
+        f: &mut Frame,
+        area: Rect,
+        visualizers: &[Box<dyn Visualizer>],
+        to: usize,
+        ctx: &DrawContext,
+    ) -> Result<()> {
+        let Some((from, progress)) = self.active() else {
+            visualizers[to].draw(
+                f,
+                area,
+                ctx.spectrum,
+                ctx.beat_info,
+                ctx.markers.for_visualizer(to),
+            );
+            return Ok(());
+        };
+
+        let kind = self.kind;
+        let outgoing = render_offscreen(
+            &mut self.outgoing,
+            area,
+            &*visualizers[from],
+            ctx.spectrum,
+            ctx.beat_info,
+            ctx.markers.for_visualizer(from),
+        )?;
+        let incoming = render_offscreen(
+            &mut self.incoming,
+            area,
+            &*visualizers[to],
+            ctx.spectrum,
+            ctx.beat_info,
+            ctx.markers.for_visualizer(to),
+        )?;
+
+        let buf = f.buffer_mut();
+        for y in 0..area.height {
+            for x in 0..area.width {
+                let (source, sx) = pick(kind, x, y, area.width, progress);
+                let cell = if source { &incoming[(sx, y)] } else { &outgoing[(sx, y)] };
+                buf[(area.x + x, area.y + y)] = cell.clone();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// For the cell at (`x`, `y`), returns whether it comes from the incoming buffer and
+/// which column of that buffer to read.
+fn pick(kind: TransitionKind, x: u16, y: u16, width: u16, progress: f32) -> (bool, u16) {
+    match kind {
+        TransitionKind::Cut => (true, x),
+        TransitionKind::Wipe => ((x as f32) < progress * width as f32, x),
+        TransitionKind::Dissolve => (cell_noise(x, y) < progress, x),
+        TransitionKind::Slide => {
+            let shift = ((progress * width as f32) as u16).min(width);
+            if x + shift < width {
+                (false, x + shift)
+            } else {
+                (true, x + shift - width)
+            }
+        }
+    }
+}
+
+/// Draws a visualizer into an off-screen terminal the size of `area` and returns its buffer.
+fn render_offscreen<'a>(
+    terminal: &'a mut Terminal<TestBackend>,
+    area: Rect,
+    visualizer: &dyn Visualizer,
+    spectrum: &FrequencySpectrum,
+    beat_info: &BeatInfo,
+    marker: Marker,
+) -> Result<&'a Buffer> {
+    let size = Rect::new(0, 0, area.width, area.height);
+    if terminal.size()? != size.as_size() {
+        terminal.backend_mut().resize(size.width, size.height);
+        terminal.resize(size)?;
+    }
+    terminal.draw(|f| visualizer.draw(f, f.area(), spectrum, beat_info, marker))?;
+    Ok(terminal.backend().buffer())
+}
+
+/// Stable per-cell pseudo-random value in 0..1, so dissolved cells don't flicker back.
+fn cell_noise(x: u16, y: u16) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x9E37_79B1) ^ (y as u32).wrapping_mul(0x85EB_CA77);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    (h & 0xFFFF) as f32 / 65535.0
+}
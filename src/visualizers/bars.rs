@@ -1,7 +1,8 @@
-use super::{BeatInfo, Visualizer};
+use super::{canvas_resolution, BeatInfo, FrequencyAxis, Visualizer};
 use ratatui::{
     layout::Rect,
     style::Color,
+    symbols::Marker,
     widgets::canvas::{Canvas, Line},
     Frame,
 };
@@ -10,12 +11,15 @@ use std::sync::Mutex;
 
 pub struct BarVisualizer {
     peaks: Mutex<Vec<f32>>,
+    /// How far the peak markers fall per frame.
+    decay: Mutex<f32>,
 }
 
 impl BarVisualizer {
     pub fn new() -> Self {
         Self {
             peaks: Mutex::new(vec![0.0; 40]),
+            decay: Mutex::new(0.5),
         }
     }
 
@@ -53,21 +57,54 @@ impl Visualizer for BarVisualizer {
         "Enhanced Bars"
     }
 
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
-        let num_bars = 40;
+    fn frequency_axis(&self) -> Option<FrequencyAxis> {
+        Some(FrequencyAxis {
+            min_hz: 20.0,
+            max_hz: 20000.0,
+        })
+    }
+
+    fn params(&self) -> &'static [&'static str] {
+        &["decay"]
+    }
+
+    fn set_param(&self, name: &str, value: f32) -> bool {
+        match name {
+            "decay" => *self.decay.lock().unwrap() = 0.1 + 1.9 * value.clamp(0.0, 1.0),
+            _ => return false,
+        }
+        true
+    }
+
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
+        // Leave a few canvas columns per bar so neighbouring bars stay distinct.
+        let (res_x, _) = canvas_resolution(area, marker);
+        let num_bars = ((res_x / 4.0) as usize).clamp(12, 64);
         let heights = self.get_log_bars(spectrum, num_bars);
         let mut peaks = self.peaks.lock().unwrap();
+        let decay = *self.decay.lock().unwrap();
+        if peaks.len() != num_bars {
+            *peaks = vec![0.0; num_bars];
+        }
 
         for i in 0..num_bars {
             let h = heights[i] * 300.0;
             if h > peaks[i] {
                 peaks[i] = h;
             } else {
-                peaks[i] = (peaks[i] - 0.5).max(0.0);
+                peaks[i] = (peaks[i] - decay).max(0.0);
             }
         }
 
         let canvas = Canvas::default()
+            .marker(marker)
             .block(
                 ratatui::widgets::Block::default()
                     .title(format!(" Style: {} ", self.name()))
@@ -1,13 +1,14 @@
-use super::{BeatInfo, Visualizer};
+use super::{canvas_resolution, point_density, BeatInfo, Visualizer};
 use ratatui::{
     layout::Rect,
     style::Color,
+    symbols::Marker,
     widgets::canvas::{Canvas, Line, Points},
     Frame,
 };
 use spectrum_analyzer::FrequencySpectrum;
 use std::sync::Mutex;
-use rand::random_range;
+use crate::clock::random_range;
 
 // --- Helper for Logarithmic Scaling ---
 fn get_log_points(spectrum: &FrequencySpectrum, num_bins: usize) -> Vec<f32> {
@@ -54,8 +55,16 @@ impl Visualizer for LiquidWorld {
         "Liquid World"
     }
 
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
-        let num_bins = 100;
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
+        let (res_x, _) = canvas_resolution(area, marker);
+        let num_bins = ((res_x / 1.5) as usize).clamp(30, 160);
         let bins = get_log_points(spectrum, num_bins);
         
         let mut fog_offset = self.fog_offset.lock().unwrap();
@@ -64,22 +73,24 @@ impl Visualizer for LiquidWorld {
         let current_fog = *fog_offset;
 
         let mut mist = self.mist.lock().unwrap();
+        let visible_mist = ((mist.len() as f64 * point_density(area, marker)) as usize).clamp(15, mist.len());
         for m in mist.iter_mut() {
             m.1 -= m.2;
             if beat_info.is_beat { m.1 += 2.5; }
             m.0 += random_range(-0.15..0.15);
-            if m.1 < 0.0 { m.1 = 50.0; m.0 = random_range(0.0..100.0); }
+            if m.1 < 0.0 { m.1 = 50.0; m.0 = random_range(0.0..num_bins as f64); }
             if m.1 > 50.0 { m.1 = 50.0; }
-            if m.0 < 0.0 { m.0 = 100.0; }
-            if m.0 > 100.0 { m.0 = 0.0; }
+            if m.0 < 0.0 { m.0 = num_bins as f64; }
+            if m.0 > num_bins as f64 { m.0 = 0.0; }
         }
 
         let canvas = Canvas::default()
+            .marker(marker)
             .block(ratatui::widgets::Block::default().title(format!(" Style: {} ", self.name())).borders(ratatui::widgets::Borders::ALL))
             .x_bounds([0.0, num_bins as f64]).y_bounds([0.0, 50.0])
             .paint(|ctx| {
                 // 1. Draw Mist Sky
-                let mist_coords: Vec<(f64, f64)> = mist.iter().map(|m| (m.0, m.1)).collect();
+                let mist_coords: Vec<(f64, f64)> = mist.iter().take(visible_mist).map(|m| (m.0, m.1)).collect();
                 ctx.draw(&Points { coords: &mist_coords, color: if beat_info.is_beat { Color::White } else { Color::DarkGray } });
 
                 // 2. Back Mountain Layer
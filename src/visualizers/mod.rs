@@ -1,4 +1,4 @@
-use ratatui::{layout::Rect, Frame};
+use ratatui::{layout::Rect, symbols::Marker, Frame};
 use spectrum_analyzer::FrequencySpectrum;
 
 pub mod waveform;
@@ -8,6 +8,7 @@ pub mod particles;
 pub mod liquid;
 pub mod waves;
 
+#[derive(Clone)]
 pub struct BeatInfo {
     pub is_beat: bool,
     pub bpm: f32,
@@ -16,5 +17,99 @@ pub struct BeatInfo {
 
 pub trait Visualizer: Send + Sync {
     fn name(&self) -> &str;
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo);
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    );
+
+    /// The frequency scale of the x axis, for visualizers that lay the spectrum out
+    /// left to right. Used by the inspection cursor.
+    fn frequency_axis(&self) -> Option<FrequencyAxis> {
+        None
+    }
+
+    /// Names of the parameters that can be adjusted live, e.g. from a MIDI controller.
+    fn params(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Sets the parameter `name` to `value`, a position from 0 to 1 across its range.
+    /// Returns false if this visualizer has no such parameter.
+    fn set_param(&self, _name: &str, _value: f32) -> bool {
+        false
+    }
+}
+
+/// Logarithmic frequency scale spanning the inner width (inside the border) of a visualizer.
+#[derive(Debug, Clone, Copy)]
+pub struct FrequencyAxis {
+    pub min_hz: f32,
+    pub max_hz: f32,
+}
+
+impl FrequencyAxis {
+    /// Frequency at `fraction` (0..1) of the way across the axis.
+    pub fn frequency_at(&self, fraction: f32) -> f32 {
+        let min_log = self.min_hz.ln();
+        let max_log = self.max_hz.ln();
+        (min_log + fraction.clamp(0.0, 1.0) * (max_log - min_log)).exp()
+    }
+}
+
+/// Dots per terminal cell (columns, rows) that a canvas marker can address.
+pub fn marker_resolution(marker: Marker) -> (u16, u16) {
+    match marker {
+        Marker::Braille | Marker::Octant => (2, 4),
+        Marker::Sextant => (2, 3),
+        Marker::Quadrant => (2, 2),
+        Marker::HalfBlock => (1, 2),
+        _ => (1, 1),
+    }
+}
+
+/// Number of addressable canvas points across and down `area`, excluding the block border.
+pub fn canvas_resolution(area: Rect, marker: Marker) -> (f64, f64) {
+    let (dx, dy) = marker_resolution(marker);
+    (
+        area.width.saturating_sub(2) as f64 * dx as f64,
+        area.height.saturating_sub(2) as f64 * dy as f64,
+    )
+}
+
+/// Canvas point density relative to a Braille canvas on an 80x24 terminal, for scaling
+/// how many particles or stars a visualizer draws.
+pub fn point_density(area: Rect, marker: Marker) -> f64 {
+    let (x, y) = canvas_resolution(area, marker);
+    (x * y / (156.0 * 88.0)).sqrt()
+}
+
+/// Whether any visualizer has a live parameter called `name`.
+pub fn has_param(name: &str) -> bool {
+    all().iter().any(|visualizer| visualizer.params().contains(&name))
+}
+
+/// Fresh instances of every registered visualizer, in menu order.
+pub fn all() -> Vec<Box<dyn Visualizer>> {
+    vec![
+        Box::new(waveform::WaveformVisualizer),
+        Box::new(bars::BarVisualizer::new()),
+        Box::new(radial::RadialVisualizer::new()),
+        Box::new(particles::VerticalParticles::new()),
+        Box::new(particles::HorizontalParticles::new()),
+        Box::new(particles::MixedParticles::new()),
+        Box::new(liquid::LiquidWorld::new()),
+        Box::new(waves::SpectralRibbons::new()),
+        Box::new(waves::ResonantHelix::new()),
+        Box::new(waves::LissajousInterference::new()),
+        Box::new(waves::LissajousEnhanced::new()),
+    ]
+}
+
+/// Names of every registered visualizer, in the same order as [`all`].
+pub fn names() -> Vec<String> {
+    all().iter().map(|v| v.name().to_string()).collect()
 }
@@ -1,13 +1,14 @@
-use super::{BeatInfo, Visualizer};
+use super::{point_density, BeatInfo, Visualizer};
 use ratatui::{
     layout::Rect,
     style::Color,
+    symbols::Marker,
     widgets::canvas::{Canvas, Points},
     Frame,
 };
 use spectrum_analyzer::FrequencySpectrum;
 use std::sync::Mutex;
-use rand::random_range;
+use crate::clock::random_range;
 
 struct Particle {
     x: f64,
@@ -79,6 +80,11 @@ fn get_log_points(spectrum: &FrequencySpectrum, num_bins: usize) -> Vec<f32> {
     bins
 }
 
+/// Spawn-rate multiplier so coarse markers aren't flooded with cell-sized particles.
+fn spawn_scale(area: Rect, marker: Marker) -> f64 {
+    point_density(area, marker).clamp(0.3, 1.5)
+}
+
 fn get_color_for_freq(x: usize, num_bins: usize) -> Color {
     let hue = x as f32 / num_bins as f32;
     if hue < 0.2 { Color::Red }
@@ -95,9 +101,17 @@ impl Visualizer for VerticalParticles {
         "Particles: Rain"
     }
 
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
         let num_bins = 80;
         let bins = get_log_points(spectrum, num_bins);
+        let spawn_scale = spawn_scale(area, marker);
         let mut particles = self.particles.lock().unwrap();
 
         for p in particles.iter_mut() {
@@ -111,7 +125,7 @@ impl Visualizer for VerticalParticles {
             let freq_boost = 1.0 + (x as f32 / num_bins as f32) * 4.0;
             let adjusted_val = val * freq_boost;
             if adjusted_val > 0.01 {
-                if random_range(0.0..1.0) < (adjusted_val * 10.0) as f64 {
+                if random_range(0.0..1.0) < (adjusted_val * 10.0) as f64 * spawn_scale {
                     particles.push(Particle {
                         x: x as f64,
                         y: 25.0,
@@ -125,6 +139,7 @@ impl Visualizer for VerticalParticles {
         }
 
         let canvas = Canvas::default()
+            .marker(marker)
             .block(ratatui::widgets::Block::default().title(format!(" Style: {} ", self.name())).borders(ratatui::widgets::Borders::ALL))
             .x_bounds([0.0, num_bins as f64]).y_bounds([0.0, 50.0])
             .paint(|ctx| {
@@ -143,9 +158,17 @@ impl Visualizer for HorizontalParticles {
         "Particles: Flow"
     }
 
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
         let num_bins = 80;
         let bins = get_log_points(spectrum, num_bins);
+        let spawn_scale = spawn_scale(area, marker);
         let mut particles = self.particles.lock().unwrap();
 
         for p in particles.iter_mut() {
@@ -159,7 +182,7 @@ impl Visualizer for HorizontalParticles {
             let freq_boost = 1.0 + (x as f32 / num_bins as f32) * 4.0;
             let adjusted_val = val * freq_boost;
             if adjusted_val > 0.01 {
-                if random_range(0.0..1.0) < (adjusted_val * 15.0) as f64 {
+                if random_range(0.0..1.0) < (adjusted_val * 15.0) as f64 * spawn_scale {
                     particles.push(Particle {
                         x: 0.0,
                         y: (x as f64 / num_bins as f64) * 50.0,
@@ -173,6 +196,7 @@ impl Visualizer for HorizontalParticles {
         }
 
         let canvas = Canvas::default()
+            .marker(marker)
             .block(ratatui::widgets::Block::default().title(format!(" Style: {} ", self.name())).borders(ratatui::widgets::Borders::ALL))
             .x_bounds([0.0, num_bins as f64]).y_bounds([0.0, 50.0])
             .paint(|ctx| {
@@ -191,9 +215,17 @@ impl Visualizer for MixedParticles {
         "Particles: Chaos"
     }
 
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
         let num_bins = 80;
         let bins = get_log_points(spectrum, num_bins);
+        let spawn_scale = spawn_scale(area, marker);
         let mut particles = self.particles.lock().unwrap();
 
         for p in particles.iter_mut() {
@@ -211,7 +243,7 @@ impl Visualizer for MixedParticles {
             let freq_boost = 1.0 + (x as f32 / num_bins as f32) * 4.0;
             let adjusted_val = val * freq_boost;
             if adjusted_val > 0.01 {
-                if random_range(0.0..1.0) < (adjusted_val * 12.0) as f64 {
+                if random_range(0.0..1.0) < (adjusted_val * 12.0) as f64 * spawn_scale {
                     particles.push(Particle {
                         x: x as f64,
                         y: 25.0,
@@ -225,6 +257,7 @@ impl Visualizer for MixedParticles {
         }
 
         let canvas = Canvas::default()
+            .marker(marker)
             .block(ratatui::widgets::Block::default().title(format!(" Style: {} ", self.name())).borders(ratatui::widgets::Borders::ALL))
             .x_bounds([0.0, num_bins as f64]).y_bounds([0.0, 50.0])
             .paint(|ctx| {
@@ -1,14 +1,15 @@
-use super::{BeatInfo, Visualizer};
+use super::{point_density, BeatInfo, Visualizer};
 use ratatui::{
     layout::Rect,
     style::Color,
+    symbols::Marker,
     widgets::canvas::{Canvas, Line, Points},
     Frame,
 };
 use spectrum_analyzer::FrequencySpectrum;
 use std::f64::consts::PI;
 use std::sync::Mutex;
-use rand::random_range;
+use crate::clock::random_range;
 
 struct Star {
     x: f64,
@@ -22,6 +23,8 @@ pub struct RadialVisualizer {
     rotation: Mutex<f64>,
     stars: Mutex<Vec<Star>>,
     core_sides: Mutex<usize>,
+    /// Rotation per frame between beats, in radians.
+    spin: Mutex<f64>,
 }
 
 impl RadialVisualizer {
@@ -44,6 +47,7 @@ impl RadialVisualizer {
             rotation: Mutex::new(0.0),
             stars: Mutex::new(stars),
             core_sides: Mutex::new(30),
+            spin: Mutex::new(0.015),
         }
     }
 
@@ -76,13 +80,32 @@ impl Visualizer for RadialVisualizer {
         "Radial Orbit"
     }
 
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+    fn params(&self) -> &'static [&'static str] {
+        &["spin"]
+    }
+
+    fn set_param(&self, name: &str, value: f32) -> bool {
+        match name {
+            "spin" => *self.spin.lock().unwrap() = 0.06 * value.clamp(0.0, 1.0) as f64,
+            _ => return false,
+        }
+        true
+    }
+
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
         let mut rotation = self.rotation.lock().unwrap();
         let mut stars = self.stars.lock().unwrap();
         let mut core_sides = self.core_sides.lock().unwrap();
 
         // 1. Update State
-        *rotation += 0.015;
+        *rotation += *self.spin.lock().unwrap();
         if beat_info.is_beat {
             *rotation += 0.08;
             *core_sides = match random_range(0..3) {
@@ -111,8 +134,12 @@ impl Visualizer for RadialVisualizer {
         let current_rotation = *rotation;
         let num_bins = 60;
         let bins = self.get_log_points(spectrum, num_bins);
+        // Fewer stars on coarse markers, where each one covers a whole cell.
+        let visible_stars = ((stars.len() as f64 * point_density(area, marker)) as usize)
+            .clamp(15, stars.len());
 
         let canvas = Canvas::default()
+            .marker(marker)
             .block(
                 ratatui::widgets::Block::default()
                     .title(format!(" Style: {} ", self.name()))
@@ -123,7 +150,11 @@ impl Visualizer for RadialVisualizer {
             .y_bounds([-60.0, 60.0])
             .paint(|ctx| {
                 // 2. Draw Nebula (Stars)
-                let star_points: Vec<(f64, f64)> = stars.iter().map(|s| (s.x, s.y)).collect();
+                let star_points: Vec<(f64, f64)> = stars
+                    .iter()
+                    .take(visible_stars)
+                    .map(|s| (s.x, s.y))
+                    .collect();
                 ctx.draw(&Points {
                     coords: &star_points,
                     color: if beat_info.is_beat { Color::White } else { Color::DarkGray },
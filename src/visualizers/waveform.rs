@@ -1,7 +1,8 @@
-use super::{BeatInfo, Visualizer};
+use super::{canvas_resolution, BeatInfo, FrequencyAxis, Visualizer};
 use ratatui::{
     layout::Rect,
     style::Color,
+    symbols::Marker,
     widgets::canvas::{Canvas, Line},
     Frame,
 };
@@ -46,13 +47,29 @@ impl Visualizer for WaveformVisualizer {
         "Mirrored Waveform"
     }
 
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
+    fn frequency_axis(&self) -> Option<FrequencyAxis> {
+        Some(FrequencyAxis {
+            min_hz: 20.0,
+            max_hz: 20000.0,
+        })
+    }
+
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
         let color = if beat_info.is_beat {
             Color::Magenta
         } else {
             Color::Cyan
         };
-        let bins = self.get_log_points(spectrum, 60);
+        // Roughly one bin per 2-3 canvas columns, so coarse markers don't smear lines together.
+        let (res_x, _) = canvas_resolution(area, marker);
+        let bins = self.get_log_points(spectrum, ((res_x / 2.5) as usize).clamp(16, 120));
 
         let mut top_points: Vec<(f64, f64)> = Vec::new();
         let mut bottom_points: Vec<(f64, f64)> = Vec::new();
@@ -66,6 +83,7 @@ impl Visualizer for WaveformVisualizer {
         }
 
         let canvas = Canvas::default()
+            .marker(marker)
             .block(
                 ratatui::widgets::Block::default()
                     .title(format!(" Style: {} ", self.name()))
@@ -1,42 +1,45 @@
-use super::{BeatInfo, Visualizer};
+use super::{canvas_resolution, BeatInfo, Visualizer};
 use ratatui::{
     layout::Rect,
     style::Color,
+    symbols::Marker,
     widgets::canvas::{Canvas, Line},
     Frame,
 };
 use spectrum_analyzer::FrequencySpectrum;
-use std::time::Instant;
+use std::time::Duration;
+use crate::{analysis::get_band_energy, clock};
 
-/// Helper to extract frequency band energy
-fn get_band_energy(spectrum: &FrequencySpectrum, min_f: f32, max_f: f32) -> f32 {
-    let mut energy = 0.0;
-    let mut count = 0;
-    for (freq, val) in spectrum.to_map().iter() {
-        let f = *freq as f32;
-        if f >= min_f && f <= max_f {
-            energy += val;
-            count += 1;
-        }
-    }
-    if count > 0 { energy / count as f32 } else { 0.0 }
+
+/// Samples along a Lissajous curve: enough to look continuous at the canvas resolution
+/// without piling many segments into each cell on coarse markers.
+fn lissajous_points(area: Rect, marker: Marker) -> usize {
+    let (res_x, res_y) = canvas_resolution(area, marker);
+    (res_x.max(res_y) as usize).clamp(50, 300)
 }
 
 // 1. --- Spectral Ribbons ---
 pub struct SpectralRibbons {
-    start_time: Instant,
+    start_time: Duration,
 }
 
 impl SpectralRibbons {
     pub fn new() -> Self {
-        Self { start_time: Instant::now() }
+        Self { start_time: clock::now() }
     }
 }
 
 impl Visualizer for SpectralRibbons {
     fn name(&self) -> &str { "Spectral Ribbons" }
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, _beat_info: &BeatInfo) {
-        let elapsed = self.start_time.elapsed().as_secs_f32();
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        _beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
+        let elapsed = clock::now().saturating_sub(self.start_time).as_secs_f32();
         
         // Define 5 frequency bands for more detail
         let sub_bass = get_band_energy(spectrum, 20.0, 60.0) * 600.0;
@@ -45,7 +48,12 @@ impl Visualizer for SpectralRibbons {
         let upper_mids = get_band_energy(spectrum, 2000.0, 6000.0) * 1500.0;
         let highs = get_band_energy(spectrum, 6000.0, 15000.0) * 3000.0;
 
+        // Segment length in canvas units: ~3 canvas points per segment at any resolution.
+        let (res_x, _) = canvas_resolution(area, marker);
+        let step = ((300.0 / res_x.max(1.0)).round() as usize).clamp(1, 10);
+
         let canvas = Canvas::default()
+            .marker(marker)
             .block(ratatui::widgets::Block::default().title(format!(" Style: {} ", self.name())).borders(ratatui::widgets::Borders::ALL))
             .x_bounds([0.0, 100.0])
             .y_bounds([-40.0, 40.0])
@@ -63,7 +71,7 @@ impl Visualizer for SpectralRibbons {
                     let mut prev_x = 0.0;
                     let mut prev_y = y_off + (elapsed * speed).sin() * amp;
                     
-                    for x in (1..=100).step_by(2) {
+                    for x in (1..=100).step_by(step) {
                         let x_f = x as f32;
                         // Multiple harmonics per ribbon for "flowing silk" effect
                         let wave1 = (x_f * 0.08 * freq + elapsed * speed).sin() * amp;
@@ -90,23 +98,32 @@ impl Visualizer for SpectralRibbons {
 
 // 2. --- Lissajous Interference (Original) ---
 pub struct LissajousInterference {
-    start_time: Instant,
+    start_time: Duration,
 }
 
 impl LissajousInterference {
     pub fn new() -> Self {
-        Self { start_time: Instant::now() }
+        Self { start_time: clock::now() }
     }
 }
 
 impl Visualizer for LissajousInterference {
     fn name(&self) -> &str { "Lissajous: Original" }
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
-        let elapsed = self.start_time.elapsed().as_secs_f32();
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
+        let elapsed = clock::now().saturating_sub(self.start_time).as_secs_f32();
         let bass = get_band_energy(spectrum, 20.0, 150.0) * 400.0;
         let highs = get_band_energy(spectrum, 2000.0, 10000.0) * 2000.0;
+        let points = lissajous_points(area, marker);
 
         let canvas = Canvas::default()
+            .marker(marker)
             .block(ratatui::widgets::Block::default().title(format!(" Style: {} ", self.name())).borders(ratatui::widgets::Borders::ALL))
             .x_bounds([-30.0, 30.0])
             .y_bounds([-30.0, 30.0])
@@ -116,8 +133,8 @@ impl Visualizer for LissajousInterference {
                 let freq_x = 2.0 + bass * 0.05;
                 let freq_y = 3.0 + highs * 0.01;
 
-                for t in 0..150 {
-                    let t_f = t as f32 * 0.12;
+                for t in 0..points {
+                    let t_f = t as f32 * (18.0 / points as f32);
                     let x = (t_f * freq_x + elapsed).sin() * 20.0;
                     let y = (t_f * freq_y + elapsed * 1.5).cos() * 20.0;
                     if t > 0 {
@@ -133,26 +150,35 @@ impl Visualizer for LissajousInterference {
 
 // 3. --- Lissajous: Enhanced (Mixed Version) ---
 pub struct LissajousEnhanced {
-    start_time: Instant,
+    start_time: Duration,
 }
 
 impl LissajousEnhanced {
     pub fn new() -> Self {
-        Self { start_time: Instant::now() }
+        Self { start_time: clock::now() }
     }
 }
 
 impl Visualizer for LissajousEnhanced {
     fn name(&self) -> &str { "Lissajous: Enhanced" }
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
-        let elapsed = self.start_time.elapsed().as_secs_f32();
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
+        let elapsed = clock::now().saturating_sub(self.start_time).as_secs_f32();
         let bass = get_band_energy(spectrum, 20.0, 150.0) * 450.0;
         let highs = get_band_energy(spectrum, 2000.0, 10000.0) * 2500.0;
+        let points = lissajous_points(area, marker);
         
         let beat_scale = if beat_info.is_beat { 1.25 } else { 1.0 };
         let base_radius = 18.0 * beat_scale;
 
         let canvas = Canvas::default()
+            .marker(marker)
             .block(ratatui::widgets::Block::default().title(format!(" Style: {} ", self.name())).borders(ratatui::widgets::Borders::ALL))
             .x_bounds([-35.0, 35.0])
             .y_bounds([-35.0, 35.0])
@@ -173,8 +199,8 @@ impl Visualizer for LissajousEnhanced {
                     let mut prev_x = 0.0;
                     let mut prev_y = 0.0;
 
-                    for t in 0..150 {
-                        let t_f = t as f32 * 0.12;
+                    for t in 0..points {
+                        let t_f = t as f32 * (18.0 / points as f32);
                         let orbit = (t_f * 8.0 + trail_elapsed * 2.0).sin() * (highs * 0.08);
                         
                         let x = (t_f * freq_x + trail_elapsed).sin() * (base_radius + orbit);
@@ -210,24 +236,35 @@ impl Visualizer for LissajousEnhanced {
 
 // 4. --- Resonant Helix Ribbons (Hybrid) ---
 pub struct ResonantHelix {
-    start_time: Instant,
+    start_time: Duration,
 }
 
 impl ResonantHelix {
     pub fn new() -> Self {
-        Self { start_time: Instant::now() }
+        Self { start_time: clock::now() }
     }
 }
 
 impl Visualizer for ResonantHelix {
     fn name(&self) -> &str { "Resonant Helix" }
-    fn draw(&self, f: &mut Frame, area: Rect, spectrum: &FrequencySpectrum, beat_info: &BeatInfo) {
-        let elapsed = self.start_time.elapsed().as_secs_f32();
+    fn draw(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        spectrum: &FrequencySpectrum,
+        beat_info: &BeatInfo,
+        marker: Marker,
+    ) {
+        let elapsed = clock::now().saturating_sub(self.start_time).as_secs_f32();
         let bass = get_band_energy(spectrum, 20.0, 150.0) * 600.0;
         let highs = get_band_energy(spectrum, 2000.0, 10000.0) * 3000.0;
         let beat_pulse = if beat_info.is_beat { 1.4 } else { 1.0 };
+        // Must divide 8 so the rungs below still land on sampled columns.
+        let (res_x, _) = canvas_resolution(area, marker);
+        let step = if res_x >= 250.0 { 1 } else if res_x >= 120.0 { 2 } else { 4 };
 
         let canvas = Canvas::default()
+            .marker(marker)
             .block(ratatui::widgets::Block::default().title(format!(" Style: {} ", self.name())).borders(ratatui::widgets::Borders::ALL))
             .x_bounds([0.0, 100.0])
             .y_bounds([-35.0, 35.0])
@@ -245,7 +282,7 @@ impl Visualizer for ResonantHelix {
 
                     let mut prev_x = 0.0;
                     
-                    for x in (0..=100).step_by(2) {
+                    for x in (0..=100).step_by(step) {
                         let x_f = x as f32;
                         let twist = 0.12 + (highs * 0.04);
                         let base_phase = x_f * twist + elapsed * 3.5 + offset;
@@ -257,7 +294,7 @@ impl Visualizer for ResonantHelix {
                         if x > 0 {
                             ctx.draw(&Line {
                                 x1: prev_x as f64,
-                                y1: strands_y[i][x-2] as f64,
+                                y1: strands_y[i][x - step] as f64,
                                 x2: x_f as f64,
                                 y2: y as f64,
                                 color,